@@ -1,9 +1,18 @@
+use super::cache::{Cache, Lookup};
+use super::outbox::{Outbox, Placeholder};
 use super::Opt;
 
+use failure::Fail;
+use pulldown_cmark::{html, Options, Parser};
+use reqwest::{Method, StatusCode};
+use serde::de::{Deserialize, DeserializeOwned, Deserializer};
+use serde::{Serialize, Serializer};
 use serde_json::Value;
 
 
 use std::io::prelude::*;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
 
 
 
@@ -11,14 +20,54 @@ use std::io::prelude::*;
 
 use url::Url;
 
-pub struct Aha<'a> {
+#[derive(Debug, Fail)]
+pub enum AhaError {
+    #[fail(display = "could not reach Aha!: {}", _0)]
+    Transport(#[cause] reqwest::Error),
+    #[fail(display = "Aha! returned {}: {}", status, body)]
+    Status { status: u16, body: String },
+    #[fail(display = "Aha! rate limit reached, retry in {} seconds", _0)]
+    RateLimited(u64),
+    #[fail(
+        display = "Aha! refused the request ({}). Check AHA_TOKEN and AHA_DOMAIN",
+        _0
+    )]
+    Auth(u16),
+    #[fail(display = "could not read the Aha! response: {}", _0)]
+    Decode(#[cause] serde_json::Error),
+    #[fail(display = "{} was not saved before going offline", _0)]
+    Offline(String),
+    #[fail(display = "could not queue the change: {}", _0)]
+    Outbox(String),
+}
+
+impl From<reqwest::Error> for AhaError {
+    fn from(error: reqwest::Error) -> Self {
+        AhaError::Transport(error)
+    }
+}
+
+impl From<serde_json::Error> for AhaError {
+    fn from(error: serde_json::Error) -> Self {
+        AhaError::Decode(error)
+    }
+}
+
+// cheap to clone, the background jobs each take a copy
+#[derive(Clone)]
+pub struct Aha {
     pub domain: String,
     pub client: reqwest::Client,
     pub user_email: String,
-    pub opt: &'a Opt,
+    pub verbose: bool,
+    pub dry_run: bool,
+    pub offline: bool,
+    pub dry_run_log: Arc<Mutex<Vec<String>>>,
+    pub cache: Cache,
+    pub outbox: Outbox,
 }
 
-impl<'a> Aha<'a> {
+impl Aha {
     pub fn url_builder(&self) -> Url {
         let uri = format!("https://{}.aha.io/api/v1/", self.domain);
         Url::parse(&uri).unwrap()
@@ -48,98 +97,316 @@ impl<'a> Aha<'a> {
             .timeout(std::time::Duration::from_secs(50))
             .build()
             .unwrap();
+        let home = dirs::home_dir();
+        // offline has nothing but the cache to browse
+        let cache = match home.as_ref() {
+            Some(home) if !opt.no_cache || opt.offline => Cache::new(
+                home.join(".aha_cli_responses"),
+                std::time::Duration::from_secs(opt.cache_ttl),
+            ),
+            _ => Cache::disabled(),
+        };
         Aha {
             client,
             domain,
             user_email: email,
-            opt,
+            verbose: opt.verbose,
+            dry_run: opt.dry_run,
+            offline: opt.offline,
+            dry_run_log: Arc::new(Mutex::new(vec![])),
+            cache,
+            outbox: Outbox::new(home.map(|home| home.join(".aha_cli_outbox.jsonl"))),
         }
     }
 
-    pub fn projects(&self) -> Vec<Value> {
-        let projects_url = self.url_builder().join("products?per_page=200").unwrap();
-        let projects = self
-            .get(projects_url, "products".to_string())
-            .expect("Can not load projects. Check your domain and api keys");
-        projects.as_array().unwrap().to_vec()
+    pub fn products_url(&self) -> Url {
+        self.url_builder().join("products").unwrap()
     }
-    pub fn releases(&self, project_id: String) -> Vec<Value> {
-        let releases_url = self
-            .url_builder()
+
+    pub fn projects(&self) -> Result<Vec<Product>, AhaError> {
+        self.get_all(self.products_url(), "products".to_string())
+    }
+    pub fn releases_url(&self, project_id: String) -> Url {
+        self.url_builder()
             .join("products/")
             .unwrap()
             .join(&format!("{}/", project_id))
             .unwrap()
-            .join("releases?exclude_shipped=true&per_page=200")
-            .unwrap();
-        let releases = self
-            .get(releases_url, "releases".to_string())
-            .expect("Can not load release. Check your access in Aha!");
-        releases.as_array().unwrap().to_vec()
+            .join("releases?exclude_shipped=true")
+            .unwrap()
     }
 
-    pub fn features(&self, project_id: String) -> Vec<Value> {
-        let releases_url = self
-            .url_builder()
+    pub fn releases(&self, project_id: String) -> Result<Vec<Release>, AhaError> {
+        self.get_all(self.releases_url(project_id), "releases".to_string())
+    }
+
+    pub fn features_url(&self, release_id: String) -> Url {
+        self.url_builder()
             .join("releases/")
             .unwrap()
-            .join(&format!("{}/", project_id))
+            .join(&format!("{}/", release_id))
+            .unwrap()
+            .join("features?fields=*")
             .unwrap()
-            .join("features?per_page=200&fields=*")
-            .unwrap();
-        let releases = self
-            .get(releases_url, "features".to_string())
-            .expect("Can not load features. Check your access in Aha!");
-        releases.as_array().unwrap().to_vec()
     }
-    pub fn send_feature(&self, feature: &FeatureCreate) -> Result<Value, serde_json::Error> {
+
+    pub fn features(&self, release_id: String) -> Result<Vec<Feature>, AhaError> {
+        self.get_all(self.features_url(release_id), "features".to_string())
+    }
+    pub fn search_url(&self, query: &str, product_id: Option<String>) -> Url {
+        let mut search_url = match product_id {
+            Some(id) => self
+                .url_builder()
+                .join("products/")
+                .unwrap()
+                .join(&format!("{}/", id))
+                .unwrap()
+                .join("features")
+                .unwrap(),
+            None => self.url_builder().join("features").unwrap(),
+        };
+        search_url
+            .query_pairs_mut()
+            .append_pair("q", query)
+            .append_pair("per_page", "50");
+        search_url
+    }
+
+    pub fn feature(&self, feature_id: String) -> Result<Feature, AhaError> {
+        Ok(serde_json::from_value(
+            self.get_json(feature_id, "feature".to_string())?,
+        )?)
+    }
+
+    pub fn requirement(&self, requirement_id: String) -> Result<Requirement, AhaError> {
+        Ok(serde_json::from_value(
+            self.get_json(requirement_id, "requirement".to_string())?,
+        )?)
+    }
+
+    pub fn release(&self, release_id: String) -> Result<Release, AhaError> {
+        Ok(serde_json::from_value(
+            self.get_json(release_id, "release".to_string())?,
+        )?)
+    }
+
+    pub fn send_feature(&self, feature: &FeatureCreate) -> Result<Feature, AhaError> {
         let uri = format!("https://{}.aha.io/api/v1/features", self.domain);
-        let response = self.client.post(&uri).json(&feature).send();
-        let content = response.unwrap().text();
-        if self.opt.verbose {
-            println!("created {:?}", content);
-        }
-        serde_json::from_str(&content.unwrap_or("".to_string()))
+        let created = self.send(Method::POST, uri, feature, "feature")?;
+        Ok(serde_json::from_value(created)?)
     }
 
     pub fn send_requirement(
         &self,
         feature_ref: String,
         requirement: &RequirementCreate,
-    ) -> Result<Value, serde_json::Error> {
+    ) -> Result<Requirement, AhaError> {
         let uri = format!(
             "https://{}.aha.io/api/v1/features/{}/requirements",
             self.domain, feature_ref
         );
+        let created = self.send(Method::POST, uri, requirement, "requirement")?;
+        Ok(serde_json::from_value(created)?)
+    }
 
-        let response = self.client.post(&uri).json(&requirement).send();
-        let content = response.unwrap().text();
-        if self.opt.verbose {
-            println!("created {:?}", content);
-        }
-        serde_json::from_str(&content.unwrap_or("".to_string()))
+    // record is features or requirements
+    pub fn comments_url(&self, record: &str, id: &str) -> Url {
+        self.url_builder()
+            .join(&format!("{}/", record))
+            .unwrap()
+            .join(&format!("{}/", id))
+            .unwrap()
+            .join("comments")
+            .unwrap()
+    }
+
+    pub fn send_comment(
+        &self,
+        record: &str,
+        id: &str,
+        comment: &CommentCreate,
+    ) -> Result<Comment, AhaError> {
+        let uri = self.comments_url(record, id).to_string();
+        let created = self.send(Method::POST, uri, comment, "comment")?;
+        Ok(serde_json::from_value(created)?)
+    }
+
+    // record is features or requirements
+    pub fn todos_url(&self, record: &str, id: &str) -> Url {
+        self.url_builder()
+            .join(&format!("{}/", record))
+            .unwrap()
+            .join(&format!("{}/", id))
+            .unwrap()
+            .join("tasks")
+            .unwrap()
+    }
+
+    pub fn todos(&self, record: &str, id: &str) -> Result<Vec<Todo>, AhaError> {
+        self.get_saved(self.todos_url(record, id), "tasks".to_string())
+    }
+
+    pub fn send_todo(&self, record: &str, id: &str, todo: &TodoCreate) -> Result<Todo, AhaError> {
+        let uri = self.todos_url(record, id).to_string();
+        let created = self.send(Method::POST, uri, todo, "task")?;
+        Ok(serde_json::from_value(created)?)
+    }
+
+    pub fn update_todo(&self, todo_id: &str, update: &TodoUpdate) -> Result<(), AhaError> {
+        let uri = format!("https://{}.aha.io/api/v1/tasks/{}", self.domain, todo_id);
+        self.send(Method::PUT, uri, update, "task")?;
+        Ok(())
+    }
+
+    pub fn workflows(&self, product_id: String) -> Result<Vec<Workflow>, AhaError> {
+        let workflows_url = self
+            .url_builder()
+            .join("products/")
+            .unwrap()
+            .join(&format!("{}/", product_id))
+            .unwrap()
+            .join("workflows")
+            .unwrap();
+        self.get_saved(workflows_url, "workflows".to_string())
+    }
+
+    pub fn users(&self, product_id: String) -> Result<Vec<User>, AhaError> {
+        let users_url = self
+            .url_builder()
+            .join("products/")
+            .unwrap()
+            .join(&format!("{}/", product_id))
+            .unwrap()
+            .join("project_users")
+            .unwrap();
+        let project_users: Vec<ProjectUser> =
+            self.get_saved(users_url, "project_users".to_string())?;
+        Ok(project_users
+            .into_iter()
+            .map(|member| member.user)
+            .collect())
+    }
+
+    pub fn update_feature(
+        &self,
+        feature_id: String,
+        update: &FeatureUpdate,
+    ) -> Result<Feature, AhaError> {
+        let uri = format!(
+            "https://{}.aha.io/api/v1/features/{}",
+            self.domain, feature_id
+        );
+        let updated = self.send(Method::PUT, uri, update, "feature")?;
+        Ok(serde_json::from_value(updated)?)
     }
 
-    pub fn get(&self, url: Url, base: String) -> Result<Value, serde_json::Error> {
-        let uri = url.to_string();
-        if self.opt.verbose {
-            println!("{} url: {}", base, uri);
+    pub fn update_requirement(
+        &self,
+        requirement_id: String,
+        update: &FeatureUpdate,
+    ) -> Result<Requirement, AhaError> {
+        let uri = format!(
+            "https://{}.aha.io/api/v1/requirements/{}",
+            self.domain, requirement_id
+        );
+        let updated = self.send(Method::PUT, uri, update, "requirement")?;
+        Ok(serde_json::from_value(updated)?)
+    }
+
+    // every write goes through here. with --dryrun the request is logged and a
+    // response is made up from the body instead
+    pub fn send<T: Serialize>(
+        &self,
+        method: Method,
+        uri: String,
+        body: &T,
+        base: &str,
+    ) -> Result<Value, AhaError> {
+        let body = serde_json::to_value(body)?;
+        if self.dry_run {
+            let mut log = self.dry_run_log.lock().unwrap();
+            log.push(format!("{} {} {}", method, uri, body));
+            let created = Placeholder::new("dryrun", log.len());
+            return Ok(placeholder_record(body, &method, &uri, Some(&created)));
         }
-        let response = self.client.get(&uri).send();
-        let content = response.unwrap().text();
-        if self.opt.verbose {
-            println!("{} text {:?}", base, content);
+        if self.offline {
+            // replay checks the record against the copy this change was made on
+            let seen = if method == Method::PUT {
+                uri.rsplit('/')
+                    .next()
+                    .and_then(|record| self.cache.updated_at(record))
+            } else {
+                None
+            };
+            let entry = self
+                .outbox
+                .push(&method, &uri, base, &body, seen)
+                .map_err(|e| AhaError::Outbox(e.to_string()))?;
+            return Ok(placeholder_record(
+                body,
+                &method,
+                &uri,
+                entry.placeholder.as_ref(),
+            ));
         }
-        let feature: Result<Value, _> = serde_json::from_str(&content.unwrap_or("".to_string()));
-        if let Ok(mut fe) = feature {
-            Ok(fe[base].take())
+        let response = self.client.request(method, &uri).json(&body).send();
+        let mut record = read_response(response, base, self.verbose)?;
+        Ok(record[base].take())
+    }
+
+    pub fn get(&self, url: Url, base: String) -> Result<Value, AhaError> {
+        let mut fe = if self.offline {
+            read_saved(&self.cache, &url)?
         } else {
-            let ex: Result<Value, serde_json::Error> = Err(feature.unwrap_err());
-            ex
+            fetch(&self.client, &url, &base, self.verbose)?
+        };
+        Ok(fe[base].take())
+    }
+
+    // a single request that can be sent from another thread
+    pub fn request<T: DeserializeOwned>(&self, url: Url, base: String) -> Request<T> {
+        Request {
+            client: self.client.clone(),
+            url,
+            base,
+            verbose: self.verbose,
+            cache: self.cache.clone(),
+            offline: self.offline,
+            record: PhantomData,
+        }
+    }
+
+    pub fn pages<T: DeserializeOwned>(&self, url: Url, base: String) -> Pages<T> {
+        Pages {
+            client: self.client.clone(),
+            url,
+            base,
+            verbose: self.verbose,
+            page: 1,
+            total_pages: None,
+            cache: self.cache.clone(),
+            lookup: Lookup::Network,
+            offline: self.offline,
+            stale: false,
+            record: PhantomData,
         }
     }
 
-    pub fn get_json(&self, end_path: String, base: String) -> Result<Value, serde_json::Error> {
+    // every record from every page
+    pub fn get_all<T: DeserializeOwned>(&self, url: Url, base: String) -> Result<Vec<T>, AhaError> {
+        self.pages(url, base).all()
+    }
+
+    // always from Aha when online, saved so the pickers work offline
+    pub fn get_saved<T: DeserializeOwned>(
+        &self,
+        url: Url,
+        base: String,
+    ) -> Result<Vec<T>, AhaError> {
+        self.pages(url, base).cached(Lookup::Refresh).all()
+    }
+
+    pub fn json_url(&self, end_path: String, base: &str) -> Url {
         let uri = format!("https://{}.aha.io/api/v1/", self.domain);
         let url = Url::parse(&uri).unwrap();
 
@@ -148,8 +415,233 @@ impl<'a> Aha<'a> {
         } else {
             "".to_string()
         };
-        let url = url.join(&format!("{}{}{}", base, "s", api_url)).unwrap();
-        self.get(url, base)
+        url.join(&format!("{}{}{}", base, "s", api_url)).unwrap()
+    }
+
+    pub fn get_json(&self, end_path: String, base: String) -> Result<Value, AhaError> {
+        self.get(self.json_url(end_path, &base), base)
+    }
+}
+
+fn fetch(
+    client: &reqwest::Client,
+    url: &Url,
+    base: &str,
+    verbose: bool,
+) -> Result<Value, AhaError> {
+    let uri = url.to_string();
+    if verbose {
+        eprintln!("{} url: {}", base, uri);
+    }
+    read_response(client.get(&uri).send(), base, verbose)
+}
+
+// json body of a successful response, failed statuses become errors
+fn read_response(
+    response: reqwest::Result<reqwest::Response>,
+    base: &str,
+    verbose: bool,
+) -> Result<Value, AhaError> {
+    let mut response = response?;
+    let status = response.status();
+    let content = response.text()?;
+    if verbose {
+        eprintln!("{} text {:?}", base, content);
+    }
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok());
+    status_result(status, retry_after, &content)
+}
+
+// maps a status and body to the json or the matching AhaError
+fn status_result(
+    status: StatusCode,
+    retry_after: Option<&str>,
+    content: &str,
+) -> Result<Value, AhaError> {
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(AhaError::Auth(status.as_u16())),
+        StatusCode::TOO_MANY_REQUESTS => {
+            let retry_after = retry_after
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(60);
+            Err(AhaError::RateLimited(retry_after))
+        }
+        status if !status.is_success() => {
+            // aha puts a message in the errors block, fall back to the raw body
+            let body = serde_json::from_str::<Value>(&content)
+                .ok()
+                .and_then(|json| json["errors"]["message"].as_str().map(String::from))
+                .unwrap_or_else(|| content.chars().take(200).collect());
+            Err(AhaError::Status {
+                status: status.as_u16(),
+                body,
+            })
+        }
+        _ => Ok(serde_json::from_str(content)?),
+    }
+}
+
+fn read_saved(cache: &Cache, url: &Url) -> Result<Value, AhaError> {
+    cache
+        .read(url)
+        .map(|(response, _)| response)
+        .ok_or_else(|| AhaError::Offline(url.to_string()))
+}
+
+// shape a request body like the record aha would send back
+fn placeholder_record(
+    mut body: Value,
+    method: &Method,
+    uri: &str,
+    created: Option<&Placeholder>,
+) -> Value {
+    if let Some(email) = body["assigned_to_user"].as_str().map(String::from) {
+        body["assigned_to_user"] = json!({ "name": email, "email": email });
+    }
+    if let Some(text) = body["description"].as_str().map(String::from) {
+        body["description"] = json!({ "body": text });
+    }
+    if let Some(fields) = body["custom_fields"].as_object().cloned() {
+        body["custom_fields"] = fields
+            .into_iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect();
+    }
+    if *method == Method::PUT {
+        // updates are sent to the record url
+        body["id"] = json!(uri.rsplit('/').next().unwrap_or(""));
+    } else if let Some(created) = created {
+        body["id"] = json!(created.id);
+        body["reference_num"] = json!(created.reference_num);
+    }
+    body["url"] = json!(uri);
+    body
+}
+
+// walks the pagination block of a list endpoint one page at a time
+pub struct Pages<T> {
+    client: reqwest::Client,
+    url: Url,
+    base: String,
+    verbose: bool,
+    page: u64,
+    total_pages: Option<u64>,
+    cache: Cache,
+    lookup: Lookup,
+    offline: bool,
+    stale: bool,
+    record: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> Pages<T> {
+    pub fn cached(mut self, lookup: Lookup) -> Pages<T> {
+        self.lookup = lookup;
+        self
+    }
+
+    // true once a page came from a cached copy older than the ttl
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    // the same pages again from Aha
+    pub fn refresh(&self) -> Pages<T> {
+        Pages {
+            client: self.client.clone(),
+            url: self.url.clone(),
+            base: self.base.clone(),
+            verbose: self.verbose,
+            page: 1,
+            total_pages: None,
+            cache: self.cache.clone(),
+            lookup: Lookup::Refresh,
+            offline: self.offline,
+            stale: false,
+            record: PhantomData,
+        }
+    }
+
+    // the rest of the records, stops at the first error
+    pub fn all(&mut self) -> Result<Vec<T>, AhaError> {
+        let mut records = vec![];
+        for page in self {
+            records.extend(page?);
+        }
+        Ok(records)
+    }
+
+    // offline every lookup is served from the cache, however old
+    fn load(&mut self, url: &Url) -> Result<Value, AhaError> {
+        if self.offline {
+            return read_saved(&self.cache, url);
+        }
+        if self.lookup == Lookup::Cached {
+            if let Some((response, stale)) = self.cache.read(url) {
+                self.stale |= stale;
+                return Ok(response);
+            }
+        }
+        let response = fetch(&self.client, url, &self.base, self.verbose)?;
+        if self.lookup != Lookup::Network {
+            self.cache.write(url, &response);
+        }
+        Ok(response)
+    }
+}
+
+pub struct Request<T> {
+    client: reqwest::Client,
+    url: Url,
+    base: String,
+    verbose: bool,
+    cache: Cache,
+    offline: bool,
+    record: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> Request<T> {
+    pub fn send(self) -> Result<T, AhaError> {
+        let mut response = if self.offline {
+            read_saved(&self.cache, &self.url)?
+        } else {
+            fetch(&self.client, &self.url, &self.base, self.verbose)?
+        };
+        Ok(serde_json::from_value(response[self.base.as_str()].take())?)
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for Pages<T> {
+    type Item = Result<Vec<T>, AhaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(total) = self.total_pages {
+            if self.page > total {
+                return None;
+            }
+        }
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .append_pair("per_page", "200")
+            .append_pair("page", &self.page.to_string());
+        let mut response = match self.load(&url) {
+            Ok(response) => response,
+            Err(e) => {
+                // stop after an error
+                self.total_pages = Some(0);
+                return Some(Err(e));
+            }
+        };
+        let pagination = &response["pagination"];
+        let current = pagination["current_page"].as_u64().unwrap_or(self.page);
+        self.total_pages = Some(pagination["total_pages"].as_u64().unwrap_or(current));
+        self.page = current + 1;
+        match response[self.base.as_str()].take() {
+            Value::Null => Some(Ok(vec![])),
+            records => Some(serde_json::from_value(records).map_err(AhaError::from)),
+        }
     }
 }
 
@@ -157,6 +649,7 @@ impl<'a> Aha<'a> {
 #[derive(Serialize, Debug, Deserialize)]
 pub struct FeatureCreate {
     pub name: String,
+    #[serde(serialize_with = "markdown_html")]
     pub description: String,
     pub release_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -197,6 +690,7 @@ impl FeatureCreate {
 #[derive(Serialize, Debug, Deserialize)]
 pub struct RequirementCreate {
     pub name: String,
+    #[serde(serialize_with = "markdown_html")]
     pub description: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub custom_fields: Option<CustomNotes>,
@@ -224,8 +718,13 @@ impl RequirementCreate {
 
 // keep
 #[derive(Serialize, Debug, Deserialize)]
-pub struct FeatureUpdateCreate {
+pub struct FeatureUpdate {
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "optional_markdown_html"
+    )]
     pub description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub assigned_to_user: Option<String>,
@@ -235,15 +734,16 @@ pub struct FeatureUpdateCreate {
     pub workflow_status: Option<WorkflowStatusUpdate>,
 }
 
-// keep
-#[derive(Serialize, Debug, Deserialize)]
-pub struct FeatureUpdate {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    assigned_to_user: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    custom_fields: Option<CustomFieldGithub>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    workflow_status: Option<WorkflowStatusUpdate>,
+impl FeatureUpdate {
+    pub fn new() -> FeatureUpdate {
+        FeatureUpdate {
+            name: None,
+            description: None,
+            assigned_to_user: None,
+            custom_fields: None,
+            workflow_status: None,
+        }
+    }
 }
 //keep
 #[derive(Serialize, Debug, Deserialize)]
@@ -255,11 +755,506 @@ pub struct WorkflowStatusUpdate {
 #[derive(Serialize, Debug, Deserialize)]
 pub struct CustomNotes {
     #[serde(rename = "release_notes1")]
-    notes: String,
+    pub notes: String,
 }
 // kepp
 #[derive(Serialize, Debug, Deserialize)]
 pub struct CustomFieldGithub {
     #[serde(rename = "pull_request")]
-    github_url: String,
+    pub github_url: String,
+}
+
+// descriptions are written as markdown, aha stores html. html2md turns it back for display
+pub fn markdown_to_html(markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    let mut body = String::new();
+    html::push_html(&mut body, Parser::new_ext(markdown, options));
+    body
+}
+
+fn markdown_html<S: Serializer>(markdown: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&markdown_to_html(markdown))
+}
+
+fn optional_markdown_html<S: Serializer>(
+    markdown: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match markdown {
+        Some(markdown) => markdown_html(markdown, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+// aha returns null for missing objects and lists
+fn null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Product {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    pub reference_prefix: Option<String>,
+    pub product_line: Option<bool>,
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Release {
+    #[serde(default)]
+    pub id: String,
+    pub reference_num: Option<String>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    pub product_id: Option<String>,
+    pub release_date: Option<String>,
+    pub parking_lot: Option<bool>,
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WorkflowStatus {
+    pub id: Option<String>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    pub color: Option<String>,
+    pub position: Option<i64>,
+    pub complete: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Workflow {
+    pub id: Option<String>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub workflow_statuses: Vec<WorkflowStatus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct User {
+    pub id: Option<String>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    pub email: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Comment {
+    pub id: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub body: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub created_at: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub user: User,
+    // replies point at the comment they answer
+    #[serde(default)]
+    pub parent_id: Option<String>,
+}
+
+// aha calls to-dos tasks
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Todo {
+    pub id: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub status: String,
+}
+
+impl Todo {
+    pub fn is_done(&self) -> bool {
+        self.status == TODO_DONE
+    }
+}
+
+pub const TODO_DONE: &str = "completed";
+pub const TODO_PENDING: &str = "pending";
+
+#[derive(Serialize, Debug)]
+pub struct TodoCreate {
+    pub name: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TodoUpdate {
+    pub status: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CommentCreate {
+    #[serde(serialize_with = "markdown_html")]
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProjectUser {
+    pub id: Option<String>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub user: User,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Description {
+    pub id: Option<String>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CustomField {
+    #[serde(default)]
+    pub key: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub value: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Requirement {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub reference_num: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub description: Description,
+    pub workflow_status: Option<WorkflowStatus>,
+    pub assigned_to_user: Option<User>,
+    pub url: Option<String>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub custom_fields: Vec<CustomField>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Feature {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub reference_num: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub description: Description,
+    pub workflow_status: Option<WorkflowStatus>,
+    pub assigned_to_user: Option<User>,
+    pub url: Option<String>,
+    pub product_id: Option<String>,
+    pub release: Option<Release>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub requirements: Vec<Requirement>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub custom_fields: Vec<CustomField>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub tags: Vec<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+fn pull_request(custom_fields: &[CustomField]) -> Option<String> {
+    custom_fields
+        .iter()
+        .find(|field| field.key == "pull_request")
+        .and_then(|field| field.value.as_str())
+        .filter(|url| !url.is_empty())
+        .map(String::from)
+}
+
+impl Feature {
+    pub fn status_name(&self) -> &str {
+        self.workflow_status
+            .as_ref()
+            .map(|status| status.name.as_str())
+            .unwrap_or("")
+    }
+
+    pub fn pull_request(&self) -> Option<String> {
+        pull_request(&self.custom_fields)
+    }
+
+    // set by the "Needs notes?" question when the feature was created
+    pub fn needs_notes(&self) -> bool {
+        self.custom_fields
+            .iter()
+            .find(|field| field.key == "release_notes1")
+            .and_then(|field| field.value.as_str())
+            .map(|value| value == "Required")
+            .unwrap_or(false)
+    }
+}
+
+impl Requirement {
+    pub fn status_name(&self) -> &str {
+        self.workflow_status
+            .as_ref()
+            .map(|status| status.name.as_str())
+            .unwrap_or("")
+    }
+
+    pub fn pull_request(&self) -> Option<String> {
+        pull_request(&self.custom_fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[test]
+    fn markdown_to_html_formats_lists_links_and_code() {
+        assert_eq!(
+            markdown_to_html("- one\n- [two](https://example.com)\n"),
+            "<ul>\n<li>one</li>\n<li><a href=\"https://example.com\">two</a></li>\n</ul>\n"
+        );
+        assert_eq!(
+            markdown_to_html("```\nlet x = 1;\n```\n"),
+            "<pre><code>let x = 1;\n</code></pre>\n"
+        );
+        assert!(markdown_to_html("~~old~~").contains("<del>old</del>"));
+        assert!(markdown_to_html("| a |\n|---|\n| 1 |\n").contains("<table>"));
+    }
+
+    #[test]
+    fn descriptions_are_sent_as_html() {
+        let mut update = FeatureUpdate::new();
+        update.description = Some("**bold**".to_string());
+        let body = serde_json::to_value(&update).unwrap();
+        assert_eq!(body["description"], "<p><strong>bold</strong></p>\n");
+        // fields that are not set are left out
+        assert!(body.get("assigned_to_user").is_none());
+    }
+
+    #[test]
+    fn placeholder_record_for_an_update_keeps_the_record_id() {
+        let body = json!({ "assigned_to_user": "a@example.com", "description": "text" });
+        let record = placeholder_record(
+            body,
+            &Method::PUT,
+            "https://example.aha.io/api/v1/features/12",
+            None,
+        );
+        assert_eq!(record["id"], "12");
+        assert_eq!(record["assigned_to_user"]["email"], "a@example.com");
+        assert_eq!(record["description"]["body"], "text");
+    }
+
+    #[test]
+    fn placeholder_record_for_a_create_uses_the_placeholder() {
+        let created = Placeholder::new("offline", 3);
+        let record = placeholder_record(
+            json!({ "name": "new", "custom_fields": { "pull_request": "url" } }),
+            &Method::POST,
+            "https://example.aha.io/api/v1/releases/5/features",
+            Some(&created),
+        );
+        assert_eq!(record["id"], json!(created.id));
+        assert_eq!(record["reference_num"], json!(created.reference_num));
+        assert_eq!(record["custom_fields"][0]["key"], "pull_request");
+        assert_eq!(record["custom_fields"][0]["value"], "url");
+    }
+
+    // pages served from a temp cache the way --offline reads them
+    fn saved_pages(name: &str, pages: &[Value]) -> (Pages<Value>, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("aha_cli_pages_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let cache = Cache::new(dir.clone(), Duration::from_secs(600));
+        let url = Url::parse("https://example.aha.io/api/v1/releases/1/features").unwrap();
+        for (i, page) in pages.iter().enumerate() {
+            let mut page_url = url.clone();
+            page_url
+                .query_pairs_mut()
+                .append_pair("per_page", "200")
+                .append_pair("page", &(i + 1).to_string());
+            cache.write(&page_url, page);
+        }
+        let pages = Pages {
+            client: reqwest::Client::new(),
+            url,
+            base: "features".to_string(),
+            verbose: false,
+            page: 1,
+            total_pages: None,
+            cache,
+            lookup: Lookup::Network,
+            offline: true,
+            stale: false,
+            record: PhantomData,
+        };
+        (pages, dir)
+    }
+
+    fn page(current: u64, total: u64, records: Value) -> Value {
+        json!({
+            "features": records,
+            "pagination": { "current_page": current, "total_pages": total },
+        })
+    }
+
+    #[test]
+    fn pages_walk_until_total_pages() {
+        let (mut pages, dir) = saved_pages(
+            "walk",
+            &[
+                page(1, 3, json!(["a", "b"])),
+                page(2, 3, json!(["c"])),
+                page(3, 3, json!(["d"])),
+            ],
+        );
+        let records: Vec<Value> = pages.all().unwrap();
+        assert_eq!(
+            records,
+            vec![json!("a"), json!("b"), json!("c"), json!("d")]
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn missing_pagination_is_a_single_page() {
+        let (pages, dir) = saved_pages(
+            "single",
+            &[json!({ "features": ["a"] }), page(2, 2, json!(["never"]))],
+        );
+        let pages: Vec<Result<Vec<Value>, AhaError>> = pages.collect();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].as_ref().unwrap(), &vec![json!("a")]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn null_records_are_an_empty_page() {
+        let (mut pages, dir) = saved_pages("null", &[page(1, 1, Value::Null)]);
+        assert_eq!(pages.next().unwrap().unwrap(), Vec::<Value>::new());
+        assert!(pages.next().is_none());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn pages_stop_after_an_error() {
+        // page 2 was never saved
+        let (mut pages, dir) = saved_pages("error", &[page(1, 3, json!(["a"]))]);
+        assert!(pages.next().unwrap().is_ok());
+        match pages.next() {
+            Some(Err(AhaError::Offline(_))) => {}
+            _ => panic!("expected the missing page to fail"),
+        }
+        assert!(pages.next().is_none());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn null_and_missing_fields_fall_back_to_defaults() {
+        let feature: Feature = serde_json::from_value(json!({
+            "id": "1",
+            "reference_num": "APP-1",
+            "name": null,
+            "description": null,
+            "assigned_to_user": null,
+            "requirements": null,
+            "custom_fields": null,
+            "tags": null,
+        }))
+        .unwrap();
+        assert_eq!(feature.name, "");
+        assert_eq!(feature.description.body, "");
+        assert!(feature.assigned_to_user.is_none());
+        assert!(feature.workflow_status.is_none());
+        assert!(feature.requirements.is_empty());
+        assert!(feature.custom_fields.is_empty());
+        assert!(feature.tags.is_empty());
+        assert_eq!(feature.status_name(), "");
+        assert!(!feature.needs_notes());
+
+        let requirement: Requirement = serde_json::from_value(json!({
+            "id": "2",
+            "reference_num": null,
+            "description": { "body": null },
+            "custom_fields": null,
+        }))
+        .unwrap();
+        assert_eq!(requirement.reference_num, "");
+        assert_eq!(requirement.description.body, "");
+        assert_eq!(requirement.status_name(), "");
+
+        let release: Release =
+            serde_json::from_value(json!({ "id": "3", "name": null, "release_date": null }))
+                .unwrap();
+        assert_eq!(release.name, "");
+        assert!(release.release_date.is_none());
+    }
+
+    #[test]
+    fn refused_requests_are_auth_errors() {
+        for code in &[401, 403] {
+            let status = StatusCode::from_u16(*code).unwrap();
+            match status_result(status, None, "denied") {
+                Err(AhaError::Auth(found)) => assert_eq!(found, *code),
+                _ => panic!("expected an auth error for {}", code),
+            }
+        }
+    }
+
+    #[test]
+    fn rate_limit_reads_retry_after() {
+        match status_result(StatusCode::TOO_MANY_REQUESTS, Some("12"), "") {
+            Err(AhaError::RateLimited(seconds)) => assert_eq!(seconds, 12),
+            _ => panic!("expected a rate limit"),
+        }
+        // missing or unreadable headers wait a minute
+        for header in &[None, Some("soon")] {
+            match status_result(StatusCode::TOO_MANY_REQUESTS, *header, "") {
+                Err(AhaError::RateLimited(seconds)) => assert_eq!(seconds, 60),
+                _ => panic!("expected a rate limit"),
+            }
+        }
+    }
+
+    #[test]
+    fn failed_status_uses_the_aha_message() {
+        let content = r#"{"errors":{"message":"Name can't be blank"}}"#;
+        match status_result(StatusCode::UNPROCESSABLE_ENTITY, None, content) {
+            Err(AhaError::Status { status, body }) => {
+                assert_eq!(status, 422);
+                assert_eq!(body, "Name can't be blank");
+            }
+            _ => panic!("expected a status error"),
+        }
+    }
+
+    #[test]
+    fn failed_status_falls_back_to_the_start_of_the_body() {
+        let content = "x".repeat(500);
+        match status_result(StatusCode::INTERNAL_SERVER_ERROR, None, &content) {
+            Err(AhaError::Status { status, body }) => {
+                assert_eq!(status, 500);
+                assert_eq!(body, "x".repeat(200));
+            }
+            _ => panic!("expected a status error"),
+        }
+    }
+
+    #[test]
+    fn success_is_the_json_body() {
+        let value = status_result(StatusCode::OK, None, r#"{"feature":{"id":"1"}}"#).unwrap();
+        assert_eq!(value["feature"]["id"], "1");
+        match status_result(StatusCode::OK, None, "<html>") {
+            Err(AhaError::Decode(_)) => {}
+            _ => panic!("expected a decode error"),
+        }
+    }
 }
@@ -1,12 +1,19 @@
-use super::key_layout::KeyLayout;
+use super::cache::Lookup;
+use super::cli;
+use super::diff::{self, Change};
+use super::editor::{Editor, EditorEvent};
+use super::fuzzy::FilteredList;
+use super::github;
+use super::key_layout::{KeyLayout, KeySequence, Sequence};
+use super::release_notes::{self, GroupBy};
 use super::util::StatefulList;
+use super::worker::{Pane, Reply, Reporter, Response, Worker};
 use super::Aha;
 
 
 use scarlet::color::RGBColor;
 
 use super::util::event::Event;
-use serde_json::Value;
 
 
 
@@ -14,13 +21,17 @@ use serde_json::Value;
 use slog::Drain;
 use std::fs::OpenOptions;
 
+use serde_json::Value;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
+use std::mem;
 use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
 use termion::event::Key;
 use tui::{
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     widgets::Text,
 };
 #[derive(PartialEq, Deserialize, Serialize, Clone)]
@@ -44,6 +55,7 @@ pub struct History {
 
 // auto select the menus based on last view
 #[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Layout {
     pub up: Option<String>,
     pub down: Option<String>,
@@ -58,6 +70,42 @@ pub struct Layout {
     pub quit: Option<String>,
     pub search: Option<String>,
     pub create: Option<String>,
+    pub filter: Option<String>,
+    pub status: Option<String>,
+    pub assign: Option<String>,
+    pub pull_request: Option<String>,
+    pub infer_pull_request: Option<String>,
+    pub release_notes: Option<String>,
+    pub external_editor: Option<String>,
+    pub edit: Option<String>,
+    pub refresh: Option<String>,
+    pub comment: Option<String>,
+    pub todos: Option<String>,
+    pub next_feature: Option<String>,
+    pub previous_feature: Option<String>,
+    pub page_down: Option<String>,
+    pub page_up: Option<String>,
+    pub half_page_down: Option<String>,
+    pub half_page_up: Option<String>,
+    pub top: Option<String>,
+    pub bottom: Option<String>,
+    // action name to a chord, refresh = "d d"
+    pub chords: Option<HashMap<String, String>>,
+    // milliseconds between redraws
+    pub tick_rate: Option<u64>,
+}
+
+pub enum EditTarget {
+    // the text goes back into the open popup's editor
+    Editor,
+    // the text replaces the selected feature's description
+    Description,
+}
+
+// text for $EDITOR, main suspends the terminal and hands the result back
+pub struct ExternalEdit {
+    pub target: EditTarget,
+    pub text: String,
 }
 
 #[derive(PartialEq)]
@@ -65,18 +113,124 @@ pub enum Popup {
     Text,
     None,
     Search,
+    Filter,
+    Pick,
+    PullRequest,
+    Edit,
+    Diff,
+    Comment,
+    Todo,
+}
+
+// the name is edited first, the description once new_name is set
+pub struct FeatureEdit {
+    pub id: String,
+    pub reference_num: String,
+    pub name: String,
+    pub description: String,
+    pub new_name: Option<String>,
+    pub new_description: Option<String>,
+}
+
+impl FeatureEdit {
+    pub fn update(&self) -> FeatureUpdate {
+        let mut update = FeatureUpdate::new();
+        match self.new_name {
+            Some(ref name) if *name != self.name => update.name = Some(name.clone()),
+            _ => {}
+        }
+        match self.new_description {
+            Some(ref description) if *description != self.description => {
+                update.description = Some(description.clone())
+            }
+            _ => {}
+        }
+        update
+    }
 }
-use super::aha::{FeatureCreate, RequirementCreate};
+
+// what choosing an item in the pick popup does
+#[derive(PartialEq)]
+pub enum PickAction {
+    Status,
+    Assign,
+    Todo,
+}
+// a line in the features list
+#[derive(Clone)]
+pub enum FeatureRow {
+    Empty,
+    Feature(Feature),
+    Requirement(Feature, Requirement),
+}
+
+impl FeatureRow {
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            FeatureRow::Empty => None,
+            FeatureRow::Feature(feature) => Some(&feature.id),
+            FeatureRow::Requirement(_, requirement) => Some(&requirement.id),
+        }
+    }
+
+    // the endpoint and id comments hang off
+    pub fn comments_path(&self) -> Option<(&'static str, &str)> {
+        match self {
+            FeatureRow::Empty => None,
+            FeatureRow::Feature(feature) => Some(("features", &feature.id)),
+            FeatureRow::Requirement(_, requirement) => Some(("requirements", &requirement.id)),
+        }
+    }
+
+    pub fn feature(&self) -> Option<&Feature> {
+        match self {
+            FeatureRow::Empty => None,
+            FeatureRow::Feature(feature) | FeatureRow::Requirement(feature, _) => Some(feature),
+        }
+    }
+}
+
+use super::aha::{
+    Comment, CommentCreate, CustomField, Description, Feature, FeatureCreate, FeatureUpdate, Pages,
+    Product, Release, Requirement, RequirementCreate, Todo, TodoCreate, TodoUpdate, User, Workflow,
+    WorkflowStatus, WorkflowStatusUpdate, TODO_DONE, TODO_PENDING,
+};
 pub struct App<'a> {
     pub layout: KeyLayout,
+    pub sequence: KeySequence,
+    pub editor: Editor,
+    pub external_edit: Option<ExternalEdit>,
+    pub feature_edit: Option<FeatureEdit>,
+    pub diff_scroll: u16,
     pub logger: slog::Logger,
-    pub items: StatefulList<(String, Value)>,
-    pub releases: StatefulList<(String, Value)>,
-    pub features: StatefulList<(String, Value)>,
+    pub items: StatefulList<(String, Product)>,
+    pub releases: StatefulList<(String, Release)>,
+    pub features: StatefulList<(String, FeatureRow)>,
+    pub search_results: StatefulList<(String, Feature)>,
+    pub search_all: bool,
+    pub worker: Worker,
+    // selections to make once a background load brings them in
+    want_release: Option<String>,
+    want_feature: Option<(String, Screen)>,
+    features_release: Option<String>,
+    stale: Vec<Pane>,
+    // comments of the shown feature or requirement
+    pub comments: Vec<Comment>,
+    comments_id: Option<String>,
+    pub todos: Vec<Todo>,
+    todos_id: Option<String>,
+    pub filter: FilteredList,
+    pub picker: StatefulList<(String, String)>,
+    pub picker_action: PickAction,
     pub feature_text: Vec<String>,
     pub feature_title: String,
     pub debug_txt: String,
     pub feature_text_formatted: Option<Vec<Text<'a>>>,
+    // detail pane scrolling, lines and height are measured while drawing
+    pub detail_scroll: u16,
+    pub detail_lines: u16,
+    pub detail_height: u16,
+    detail_id: Option<String>,
     pub active_layer: Screen,
     pub popup: Popup,
     pub text_box: String,
@@ -108,13 +262,37 @@ impl<'a> App<'a> {
         let log = slog::Logger::root(drain, o!());
         App {
             layout: KeyLayout::default(),
+            sequence: KeySequence::default(),
+            editor: Editor::single_line(),
+            external_edit: None,
+            feature_edit: None,
+            diff_scroll: 0,
             logger: log,
             popup: Popup::None,
             items: StatefulList::with_items(vec![]),
             releases: StatefulList::with_items(vec![]),
             features: StatefulList::with_items(vec![]),
+            search_results: StatefulList::with_items(vec![]),
+            search_all: false,
+            // replaced with the events sender once the ui starts
+            worker: Worker::new(mpsc::channel().0),
+            want_release: None,
+            want_feature: None,
+            features_release: None,
+            stale: vec![],
+            comments: vec![],
+            comments_id: None,
+            todos: vec![],
+            todos_id: None,
+            filter: FilteredList::new(),
+            picker: StatefulList::with_items(vec![]),
+            picker_action: PickAction::Status,
             feature_text: vec!["".to_string()],
             feature_text_formatted: None,
+            detail_scroll: 0,
+            detail_lines: 0,
+            detail_height: 0,
+            detail_id: None,
             history: None,
             debug_txt: "".to_string(),
             feature_title: "".to_string(),
@@ -132,65 +310,646 @@ impl<'a> App<'a> {
     }
 
     pub fn load_features(&mut self, release_id: String, aha: &Aha) {
-        let feature_list = aha.features(release_id.clone());
+        self.fetch_features(release_id, aha, Lookup::Cached);
+    }
+
+    // pages arrive through handle_api, a reload keeps the old rows until the first one
+    fn fetch_features(&mut self, release_id: String, aha: &Aha, lookup: Lookup) {
+        self.write_history("release".to_string(), release_id.clone());
+        if self.features_release.as_ref() != Some(&release_id) {
+            self.features =
+                StatefulList::with_items(vec![("Loading features".to_string(), FeatureRow::Empty)]);
+        }
+        self.features_release = Some(release_id.clone());
+        let mut pages = aha
+            .pages::<Feature>(aha.features_url(release_id), "features".to_string())
+            .cached(lookup);
+        self.worker.run(Pane::Features, move |reporter| {
+            if !send_feature_pages(&mut pages, reporter)
+                || !reporter.send(Response::Stale(pages.is_stale()))
+                || !pages.is_stale()
+            {
+                return;
+            }
+            // the cached copy stays up while the fresh one loads
+            if send_feature_pages(&mut pages.refresh(), reporter) {
+                reporter.send(Response::Stale(false));
+            }
+        });
+    }
 
-        self.write_history("release".to_string(), release_id);
-        self.features = StatefulList::with_items(
-            feature_list
+    fn show_features(&mut self, feature_list: Vec<Feature>, first: bool) {
+        if first {
+            // a refreshed list picks the same feature again once it shows up
+            if self.want_feature.is_none() {
+                self.want_feature = self
+                    .features
+                    .state
+                    .selected()
+                    .and_then(|i| self.features.items[i].1.id())
+                    .map(|id| (id.to_string(), self.active_layer.clone()));
+            }
+            self.features = StatefulList::with_items(vec![]);
+        }
+        self.features.items.extend(feature_rows(&feature_list));
+        self.debug_txt = format!("{} features loaded", self.features.len());
+        let found = self.want_feature.as_ref().and_then(|(feature_id, _)| {
+            self.features
+                .items
                 .iter()
-                .map(|project| {
-                    let mut vec = vec![];
-
-                    vec.push((
-                        format!(
-                            "{} - {}",
-                            project["name"].as_str().unwrap(),
-                            project["workflow_status"]["name"].as_str().unwrap(),
-                        ),
-                        project.clone(),
-                    ));
-                    if let Some(reqs) = project["requirements"].as_array() {
-                        let last = reqs.len();
-                        for (i, req) in reqs.iter().enumerate() {
-                            let brace = if i == last - 1 { "└" } else { "├" };
-                            vec.push((
-                                format!(
-                                    "{} {} - {}",
-                                    brace,
-                                    req["name"].as_str().unwrap(),
-                                    req["workflow_status"]["name"].as_str().unwrap(),
-                                ),
-                                project.clone(),
-                            ))
-                        }
-                    }
-                    vec
-                })
-                .flatten()
-                .collect(),
-        );
-        if self.features.len() == 0 {
+                .position(|x| x.1.id() == Some(feature_id.as_str()))
+        });
+        if let Some(index) = found {
+            let (_, screen) = self.want_feature.take().unwrap();
+            self.features.state.select(Some(index));
+            self.feature_text_formatted = None;
+            self.active_layer = screen;
+        }
+    }
+
+    fn features_loaded(&mut self) {
+        if self.features.items.iter().all(|x| x.1.id().is_none()) {
             self.features = StatefulList::with_items(vec![(
                 "No features loaded".to_string(),
-                serde_json::Value::Null,
+                FeatureRow::Empty,
             )]);
         }
+        // a search result that is not in the list still lands on its release
+        if let Some((_, Screen::Feature)) = self.want_feature.take() {
+            self.active_layer = Screen::Features;
+        }
     }
 
     pub fn load_releases(&mut self, project_id: String, aha: &Aha) {
-        let releases = aha.releases(project_id.clone());
-        self.write_history("project".to_string(), project_id);
+        self.releases = StatefulList::with_items(vec![]);
+        self.fetch_releases(project_id, aha, Lookup::Cached);
+    }
+
+    fn fetch_releases(&mut self, project_id: String, aha: &Aha, lookup: Lookup) {
+        self.write_history("project".to_string(), project_id.clone());
+        let mut pages = aha
+            .pages::<Release>(aha.releases_url(project_id), "releases".to_string())
+            .cached(lookup);
+        self.worker.run(Pane::Releases, move |reporter| {
+            let releases = pages.all();
+            let fresh = releases.is_ok();
+            if !reporter.send(Response::Releases(releases))
+                || (fresh && !reporter.send(Response::Stale(pages.is_stale())))
+                || !pages.is_stale()
+            {
+                return;
+            }
+            let releases = pages.refresh().all();
+            let fresh = releases.is_ok();
+            if reporter.send(Response::Releases(releases)) && fresh {
+                reporter.send(Response::Stale(false));
+            }
+        });
+    }
+
+    pub fn refresh_products(&mut self, aha: &Aha) {
+        let mut pages = aha
+            .pages::<Product>(aha.products_url(), "products".to_string())
+            .cached(Lookup::Refresh);
+        self.worker.run(Pane::Products, move |reporter| {
+            let products = pages.all();
+            let fresh = products.is_ok();
+            if reporter.send(Response::Products(products)) && fresh {
+                reporter.send(Response::Stale(false));
+            }
+        });
+    }
+
+    // the products shown at startup came from a cache entry past its ttl
+    pub fn refresh_stale_products(&mut self, aha: &Aha) {
+        self.set_stale(Pane::Products, true);
+        self.refresh_products(aha);
+    }
+
+    fn set_stale(&mut self, pane: Pane, stale: bool) {
+        self.stale.retain(|other| *other != pane);
+        if stale {
+            self.stale.push(pane);
+        }
+    }
+
+    // r reloads the list in focus from Aha
+    pub fn refresh(&mut self, aha: &Aha) {
+        match self.active_layer {
+            Screen::Project => self.refresh_products(aha),
+            Screen::Release => {
+                if let Some(i) = self.items.state.selected() {
+                    let project_id = self.items.items[i].1.id.clone();
+                    self.fetch_releases(project_id, aha, Lookup::Refresh);
+                }
+            }
+            _ => {
+                if let Some(i) = self.releases.state.selected() {
+                    let release_id = self.releases.items[i].1.id.clone();
+                    self.fetch_features(release_id, aha, Lookup::Refresh);
+                }
+            }
+        }
+    }
+
+    fn show_products(&mut self, products: Vec<Product>) {
+        let selected = self
+            .items
+            .state
+            .selected()
+            .map(|i| self.items.items[i].1.id.clone());
+        self.items = StatefulList::with_items(
+            products
+                .iter()
+                .map(|project| (project.name.clone(), project.clone()))
+                .collect(),
+        );
+        if let Some(id) = selected {
+            let index = self.items.items.iter().position(|x| x.1.id == id);
+            self.items.state.select(index);
+        }
+    }
+
+    // a saved or searched for release goes on to load its features, a refreshed list
+    // keeps its selection
+    fn show_releases(&mut self, releases: Vec<Release>, aha: &Aha) {
+        let selected = self
+            .releases
+            .state
+            .selected()
+            .map(|i| self.releases.items[i].1.id.clone());
         self.releases = StatefulList::with_items(
             releases
                 .iter()
-                .map(|project| {
-                    (
-                        project["name"].as_str().unwrap().to_string(),
-                        project.clone(),
-                    )
-                })
+                .map(|release| (release.name.clone(), release.clone()))
                 .collect(),
         );
+        if let Some(id) = selected {
+            let index = self.releases.items.iter().position(|x| x.1.id == id);
+            self.releases.state.select(index);
+        }
+        if let Some(release_id) = self.want_release.take() {
+            match self
+                .releases
+                .items
+                .iter()
+                .position(|x| x.1.id == release_id)
+            {
+                Some(index) => {
+                    self.releases.state.select(Some(index));
+                    self.active_layer = Screen::Features;
+                    self.load_features(release_id, aha);
+                }
+                None => {
+                    self.want_feature = None;
+                    self.debug_txt = "release is shipped or not visible".to_string();
+                }
+            }
+        }
+    }
+
+    // replies for cancelled or replaced loads are dropped
+    pub fn handle_api(&mut self, reply: Reply, aha: &Aha) {
+        let pane = match self.worker.pane(reply.id) {
+            Some(pane) => pane,
+            None => return,
+        };
+        match reply.response {
+            Response::Products(Ok(products)) => self.show_products(products),
+            Response::Products(Err(e)) => {
+                self.debug_txt = format!("Can not load projects: {}", e);
+            }
+            Response::Releases(Ok(releases)) => self.show_releases(releases, aha),
+            Response::Releases(Err(e)) => {
+                self.want_release = None;
+                self.debug_txt = format!("Can not load releases: {}", e);
+            }
+            Response::FirstFeatures(Ok(feature_list)) => self.show_features(feature_list, true),
+            Response::Features(Ok(feature_list)) => self.show_features(feature_list, false),
+            Response::FirstFeatures(Err(e)) | Response::Features(Err(e)) => {
+                self.debug_txt = format!("Can not load features: {}", e);
+            }
+            Response::Search(Ok(features)) => self.show_search_results(features),
+            Response::Search(Err(e)) => {
+                self.debug_txt = format!("search error: {}", e);
+            }
+            Response::SearchResult(Ok(feature)) => self.open_feature(feature, aha),
+            Response::SearchResult(Err(e)) => {
+                self.debug_txt = format!("feature error: {}", e);
+            }
+            Response::Comments(id, Ok(comments)) => {
+                if self.comments_id.as_ref() == Some(&id) {
+                    self.comments = comments;
+                    self.feature_text_formatted = None;
+                }
+            }
+            Response::Comments(_, Err(e)) => {
+                self.debug_txt = format!("Can not load comments: {}", e);
+            }
+            Response::Todos(id, Ok(todos)) => {
+                if self.todos_id.as_ref() == Some(&id) {
+                    self.todos = todos;
+                    self.feature_text_formatted = None;
+                    if self.popup == Popup::Pick && self.picker_action == PickAction::Todo {
+                        self.fill_todo_picker();
+                    }
+                }
+            }
+            Response::Todos(_, Err(e)) => {
+                self.debug_txt = format!("Can not load to-dos: {}", e);
+            }
+            Response::Workflows(current_id, Ok(workflows)) => {
+                self.show_status_picker(current_id, workflows)
+            }
+            Response::Workflows(_, Err(e)) => {
+                self.debug_txt = format!("Can not load workflows: {}", e);
+            }
+            Response::Users(Ok(users)) => self.show_assign_picker(users, aha),
+            Response::Users(Err(e)) => {
+                self.debug_txt = format!("Can not load users: {}", e);
+            }
+            Response::ReleaseNotes(release, Ok(features)) => {
+                self.save_release_notes(release, features)
+            }
+            Response::ReleaseNotes(_, Err(e)) => {
+                self.debug_txt = format!("Can not load features: {}", e);
+            }
+            Response::FeatureCreated(release_id, Ok(x)) => {
+                if self.is_local(&release_id, aha) {
+                    let feature = x.clone();
+                    self.add_local_row(&x.id, |features| features.push(feature));
+                }
+                let created = format!("feature created: {}", x.reference_num);
+                self.show_created(created, x.url, release_id, x.id, aha);
+            }
+            Response::FeatureCreated(_, Err(e)) => {
+                self.debug_txt = format!("feature error: {}", e);
+            }
+            Response::RequirementCreated(release_id, feature_ref, Ok(x)) => {
+                if self.is_local(&release_id, aha) {
+                    let requirement = x.clone();
+                    self.add_local_row(&x.id, |features| {
+                        for feature in features.iter_mut() {
+                            if feature.reference_num == feature_ref {
+                                feature.requirements.push(requirement.clone());
+                            }
+                        }
+                    });
+                }
+                let created = format!("requirement created: {}", x.reference_num);
+                self.show_created(created, x.url, release_id, x.id, aha);
+            }
+            Response::RequirementCreated(_, _, Err(e)) => {
+                self.debug_txt = format!("requirement error: {}", e);
+            }
+            Response::Status(id, name, Ok(status)) => self.status_set(id, name, status, aha),
+            Response::Status(_, _, Err(e)) => {
+                self.debug_txt = format!("status error: {}", e);
+            }
+            Response::Assigned(id, email, Ok(user)) => self.assigned(id, email, user, aha),
+            Response::Assigned(_, _, Err(e)) => {
+                self.debug_txt = format!("assign error: {}", e);
+            }
+            Response::PullRequest(id, reference, url, Ok(())) => {
+                self.pull_request_set(id, reference, url, aha)
+            }
+            Response::PullRequest(_, _, _, Err(e)) => {
+                self.debug_txt = format!("pull request error: {}", e);
+            }
+            // the popup may have been closed while github answered
+            Response::PullRequestFound(Ok(url)) => {
+                if self.popup == Popup::PullRequest {
+                    self.text_box = url;
+                    self.debug_txt = "found the pull request of the current branch".to_string();
+                }
+            }
+            Response::PullRequestFound(Err(e)) => {
+                self.debug_txt = format!("{}", e);
+            }
+            Response::Description(id, Ok(description)) => {
+                self.description_set(id, description, aha)
+            }
+            Response::Description(_, Err(e)) => {
+                self.debug_txt = format!("description error: {}", e);
+            }
+            Response::Edited(id, reference_num, Ok(updated)) => {
+                self.edited(id, reference_num, updated, aha)
+            }
+            Response::Edited(_, _, Err(e)) => {
+                self.debug_txt = format!("edit error: {}", e);
+            }
+            Response::CommentPosted(id, Ok(_)) => {
+                self.debug_txt = "comment posted".to_string();
+                // loaded again with the new comment in its place
+                if self.comments_id.as_ref() == Some(&id) {
+                    self.comments_id = None;
+                    self.load_comments(aha);
+                }
+            }
+            Response::CommentPosted(_, Err(e)) => {
+                self.debug_txt = format!("comment error: {}", e);
+            }
+            Response::TodoUpdated(todo_id, status, Ok(())) => self.todo_updated(todo_id, status),
+            Response::TodoAdded(id, Ok(todo)) => {
+                self.debug_txt = format!("to-do added: {}", todo.name);
+                if self.todos_id.as_ref() == Some(&id) {
+                    self.todos_id = None;
+                    self.load_todos(aha);
+                }
+            }
+            Response::TodoUpdated(_, _, Err(e)) | Response::TodoAdded(_, Err(e)) => {
+                self.debug_txt = format!("to-do error: {}", e);
+            }
+            Response::Stale(stale) => self.set_stale(pane, stale),
+            Response::Done => {
+                self.worker.finish(reply.id);
+                if pane == Pane::Features {
+                    self.features_loaded();
+                }
+                if pane == Pane::Comments || pane == Pane::Todos {
+                    self.feature_text_formatted = None;
+                }
+            }
+        }
+    }
+
+    // a new feature or requirement is selected once its release's list has it, if that
+    // release is still the one shown
+    fn show_created(
+        &mut self,
+        created: String,
+        url: Option<String>,
+        release_id: String,
+        id: String,
+        aha: &Aha,
+    ) {
+        self.debug_txt = format!("{} {}", created, url.unwrap_or_default());
+        if self.features_release.as_ref() == Some(&release_id) {
+            self.reload_features(id, aha);
+        }
+    }
+
+    // the shown feature's comments load once each time it is selected
+    pub fn load_comments(&mut self, aha: &Aha) {
+        // coming back to the feature loads them again
+        if self.active_layer != Screen::Feature {
+            self.comments_id = None;
+            return;
+        }
+        let (record, id) = match self.selected_record() {
+            Some(record) => record,
+            None => return,
+        };
+        if self.comments_id.as_ref() == Some(&id) {
+            return;
+        }
+        self.comments_id = Some(id.clone());
+        self.comments = vec![];
+        self.feature_text_formatted = None;
+        let mut pages = aha
+            .pages::<Comment>(aha.comments_url(record, &id), "comments".to_string())
+            .cached(Lookup::Refresh);
+        self.worker.run(Pane::Comments, move |reporter| {
+            reporter.send(Response::Comments(id, pages.all()));
+        });
+    }
+
+    fn selected_record(&self) -> Option<(&'static str, String)> {
+        self.features
+            .state
+            .selected()
+            .and_then(|i| self.features.items[i].1.comments_path())
+            .map(|(record, id)| (record, id.to_string()))
+    }
+
+    pub fn load_todos(&mut self, aha: &Aha) {
+        // coming back to the feature loads them again
+        if self.active_layer != Screen::Feature {
+            self.todos_id = None;
+            return;
+        }
+        let (record, id) = match self.selected_record() {
+            Some(record) => record,
+            None => return,
+        };
+        if self.todos_id.as_ref() == Some(&id) {
+            return;
+        }
+        self.todos_id = Some(id.clone());
+        self.todos = vec![];
+        self.feature_text_formatted = None;
+        let mut pages = aha
+            .pages::<Todo>(aha.todos_url(record, &id), "tasks".to_string())
+            .cached(Lookup::Refresh);
+        self.worker.run(Pane::Todos, move |reporter| {
+            reporter.send(Response::Todos(id, pages.all()));
+        });
+    }
+
+    // the picker rows carry the to-do id, the last one adds a new to-do
+    fn fill_todo_picker(&mut self) {
+        let selected = self.picker.state.selected();
+        let mut items: Vec<(String, String)> = self
+            .todos
+            .iter()
+            .map(|todo| {
+                let check = if todo.is_done() { "x" } else { " " };
+                (format!("[{}] {}", check, todo.name), todo.id.clone())
+            })
+            .collect();
+        items.push(("+ new to-do".to_string(), String::new()));
+        self.picker = StatefulList::with_items(items);
+        self.picker.select(selected.unwrap_or(0));
+    }
+
+    pub fn open_todo_picker(&mut self) {
+        if self.selected_record().is_none() {
+            self.debug_txt = "no feature selected".to_string();
+            return;
+        }
+        self.picker_action = PickAction::Todo;
+        self.picker = StatefulList::with_items(vec![]);
+        self.fill_todo_picker();
+        self.popup = Popup::Pick;
+    }
+
+    fn pick_todo(&mut self, todo_id: String, aha: &Aha) {
+        if todo_id.is_empty() {
+            self.editor = Editor::single_line();
+            self.text_box_title = "New to-do".to_string();
+            self.popup = Popup::Todo;
+            return;
+        }
+        // the picker stays open to check off several
+        self.popup = Popup::Pick;
+        let todo = match self.todos.iter().find(|todo| todo.id == todo_id) {
+            Some(todo) => todo,
+            None => return,
+        };
+        let status = if todo.is_done() {
+            TODO_PENDING
+        } else {
+            TODO_DONE
+        };
+        self.debug_txt = format!("marking {} {}", todo.name, status);
+        let aha = aha.clone();
+        self.worker.save(move |reporter| {
+            let update = TodoUpdate {
+                status: status.to_string(),
+            };
+            let updated = aha.update_todo(&todo_id, &update);
+            reporter.send(Response::TodoUpdated(todo_id, status.to_string(), updated));
+        });
+    }
+
+    fn todo_updated(&mut self, todo_id: String, status: String) {
+        let todo = match self.todos.iter_mut().find(|todo| todo.id == todo_id) {
+            Some(todo) => todo,
+            None => return,
+        };
+        todo.status = status;
+        self.debug_txt = format!("{} {}", todo.name, todo.status);
+        self.feature_text_formatted = None;
+        if self.popup == Popup::Pick && self.picker_action == PickAction::Todo {
+            self.fill_todo_picker();
+        }
+    }
+
+    fn add_todo(&mut self, aha: &Aha) {
+        let name = self.editor.text().trim().to_string();
+        if name.is_empty() {
+            self.debug_txt = "empty to-do not added".to_string();
+            return;
+        }
+        let (record, id) = match self.selected_record() {
+            Some(record) => record,
+            None => return,
+        };
+        self.debug_txt = "adding to-do".to_string();
+        let aha = aha.clone();
+        self.worker.save(move |reporter| {
+            let added = aha.send_todo(record, &id, &TodoCreate { name });
+            reporter.send(Response::TodoAdded(id, added));
+        });
+    }
+
+    pub fn handle_todo_popup(&mut self, event: Event<Key>, aha: &Aha) -> Option<()> {
+        match event {
+            Event::Input(input) => {
+                if input == self.layout.escape {
+                    self.popup = Popup::None;
+                    self.editor = Editor::single_line();
+                } else if let EditorEvent::Submit = self.editor.handle_key(input) {
+                    self.popup = Popup::None;
+                    self.add_todo(aha);
+                    self.editor = Editor::single_line();
+                }
+            }
+            Event::Paste(text) => {
+                self.editor.insert_str(&text);
+            }
+            Event::Api(_) => {}
+            Event::Tick => {
+                self.advance();
+            }
+        }
+        //dont break from here
+        Some(())
+    }
+
+    pub fn open_comment(&mut self) {
+        let reference_num = match self
+            .features
+            .state
+            .selected()
+            .map(|i| &self.features.items[i].1)
+        {
+            Some(FeatureRow::Feature(feature)) => feature.reference_num.clone(),
+            Some(FeatureRow::Requirement(_, requirement)) => requirement.reference_num.clone(),
+            _ => {
+                self.debug_txt = "no feature selected".to_string();
+                return;
+            }
+        };
+        self.editor = Editor::multiline();
+        self.text_box_title = format!("Comment on {}", reference_num);
+        self.popup = Popup::Comment;
+    }
+
+    fn post_comment(&mut self, aha: &Aha) {
+        let body = self.editor.text().trim().to_string();
+        if body.is_empty() {
+            self.debug_txt = "empty comment not sent".to_string();
+            return;
+        }
+        let (record, id) = match self.selected_record() {
+            Some(record) => record,
+            None => return,
+        };
+        self.debug_txt = "posting comment".to_string();
+        let aha = aha.clone();
+        self.worker.save(move |reporter| {
+            let posted = aha.send_comment(record, &id, &CommentCreate { body });
+            reporter.send(Response::CommentPosted(id, posted));
+        });
+    }
+
+    pub fn handle_comment_popup(&mut self, event: Event<Key>, aha: &Aha) -> Option<()> {
+        match event {
+            Event::Input(input) => {
+                if input == self.layout.escape {
+                    self.popup = Popup::None;
+                    self.editor = Editor::single_line();
+                } else if input == self.layout.external_editor {
+                    self.external_edit = Some(ExternalEdit {
+                        target: EditTarget::Editor,
+                        text: self.editor.text(),
+                    });
+                } else if let EditorEvent::Submit = self.editor.handle_key(input) {
+                    self.popup = Popup::None;
+                    self.post_comment(aha);
+                    self.editor = Editor::single_line();
+                }
+            }
+            Event::Paste(text) => {
+                self.editor.insert_str(&text);
+            }
+            Event::Api(_) => {}
+            Event::Tick => {
+                self.advance();
+            }
+        }
+        //dont break from here
+        Some(())
+    }
+
+    // the title of a list pane with its loading and cache state
+    pub fn pane_title(&self, title: &str, pane: Pane) -> String {
+        let title = self.worker.title(title, pane);
+        if self.stale.contains(&pane) {
+            format!("{} (stale)", title)
+        } else {
+            title
+        }
+    }
+
+    // esc while something loads stops it, true when there was something to stop
+    pub fn cancel_loading(&mut self) -> bool {
+        if !self.worker.cancel_all() {
+            return false;
+        }
+        self.want_release = None;
+        self.want_feature = None;
+        if self.features.items.iter().all(|x| x.1.id().is_none()) {
+            self.features = StatefulList::with_items(vec![(
+                "Loading cancelled".to_string(),
+                FeatureRow::Empty,
+            )]);
+            // the release has to be loaded again
+            self.features_release = None;
+        }
+        self.debug_txt = "loading cancelled".to_string();
+        true
     }
     pub fn write_history(&mut self, key: String, value: String) {
         if self.history.is_none() {
@@ -230,41 +989,57 @@ impl<'a> App<'a> {
         };
     }
 
-    fn get_key_from(&self, input: &str) -> Key {
-        if input.len() == 1 {
-            Key::Char(input.as_bytes()[0] as char)
-        } else if input == "up" {
-            Key::Up
-        } else if input == "down" {
-            Key::Down
-        } else if input == "left" {
-            Key::Left
-        } else if input == "right" {
-            Key::Right
-        } else if input == "esc" {
-            Key::Esc
-        } else if input == "none" {
-            Key::Null
-        } else if input == "\n" {
-            Key::Char('\n')
-        } else if input.starts_with("alt+") {
-            let text = input.splitn(2, '+').collect::<Vec<_>>();
-            let text = text.last().unwrap();
-            Key::Alt(text.as_bytes()[0] as char)
-        } else if input.starts_with("ctrl+") {
-            let text = input.splitn(2, '+').collect::<Vec<_>>();
-            let text = text.last().unwrap();
-            Key::Ctrl(text.as_bytes()[0] as char)
-        } else {
-            Key::Null
+    fn get_key_from(input: &str) -> Result<Key, failure::Error> {
+        let single = |text: &str| -> Result<char, failure::Error> {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => bail!("expected a single character after + in {}", input),
+            }
+        };
+        if input.chars().count() == 1 {
+            return Ok(Key::Char(input.chars().next().unwrap()));
         }
+        let name = input.to_lowercase();
+        let key = match name.as_str() {
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "esc" | "escape" => Key::Esc,
+            "none" => Key::Null,
+            "\\n" | "enter" => Key::Char('\n'),
+            "tab" => Key::Char('\t'),
+            "backtab" => Key::BackTab,
+            "space" => Key::Char(' '),
+            "backspace" => Key::Backspace,
+            "delete" | "del" => Key::Delete,
+            "insert" => Key::Insert,
+            "home" => Key::Home,
+            "end" => Key::End,
+            "pgup" | "pageup" => Key::PageUp,
+            "pgdown" | "pagedown" => Key::PageDown,
+            _ => {
+                if name.starts_with("alt+") {
+                    Key::Alt(single(&input[4..])?)
+                } else if name.starts_with("ctrl+") {
+                    // termion only reports lowercase ctrl keys
+                    Key::Ctrl(single(&input[5..])?.to_ascii_lowercase())
+                } else if name.starts_with('f') {
+                    match name[1..].parse::<u8>() {
+                        Ok(n) if n >= 1 && n <= 12 => Key::F(n),
+                        _ => bail!("unknown key {}", input),
+                    }
+                } else {
+                    bail!("unknown key {}", input)
+                }
+            }
+        };
+        Ok(key)
     }
 
-    pub fn load_layout(&mut self, file: String) {
-        let value: Layout = toml::from_str(&file).unwrap();
-        if let Some(x) = value.up {
-            self.layout.up = self.get_key_from(&x);
-        }
+    pub fn load_layout(&mut self, file: String) -> Result<(), failure::Error> {
+        apply_layout(&mut self.layout, &file)
     }
 
     pub fn load_history(&mut self, file: String, aha: &Aha) {
@@ -272,46 +1047,15 @@ impl<'a> App<'a> {
         let return_value = value.clone();
         if let Some(project) = value.project {
             let project = project;
-            if let Some(index) = self.items.items.iter().position(|x| x.1["id"] == project) {
-                let project_id = self.items.items[index].1["id"]
-                    .as_str()
-                    .unwrap()
-                    .to_string();
+            if let Some(index) = self.items.items.iter().position(|x| x.1.id == project) {
+                let project_id = self.items.items[index].1.id.clone();
                 self.items.state.select(Some(index));
+                // the release and feature are selected as their lists arrive
+                self.want_release = value.release;
+                self.want_feature = value.feature.map(|feature| (feature, Screen::Features));
                 self.load_releases(project_id, &aha);
 
                 self.active_layer = Screen::Release;
-                if let Some(release) = value.release {
-                    if let Some(index) = self
-                        .releases
-                        .items
-                        .iter()
-                        .position(|x| x.1["id"] == release)
-                    {
-                        let release_id = self.releases.items[index].1["id"]
-                            .as_str()
-                            .unwrap()
-                            .to_string();
-
-                        self.releases.state.select(Some(index));
-                        self.load_features(release_id, &aha);
-
-                        self.active_layer = Screen::Features;
-                    }
-
-                    if let Some(feature) = value.feature {
-                        if let Some(index) = self
-                            .features
-                            .items
-                            .iter()
-                            .position(|x| x.1["id"] == feature)
-                        {
-                            // todo: need to format the selected feature and write the history
-                            self.active_layer = Screen::Features;
-                            self.features.state.select(Some(index));
-                        }
-                    }
-                }
             }
         }
 
@@ -339,24 +1083,91 @@ impl<'a> App<'a> {
                     self.layout.left, self.layout.left_arrow
                 )),
                 Text::raw(format!("{:?} - exit\n", self.layout.quit)),
-                Text::raw(format!("{:?} - to close popups\n", self.layout.escape)),
-            ];
-            if self.active_layer != Screen::Project {
-                base.push(Text::raw("\nRelease Actions:\n"));
-                base.push(Text::raw(format!(
-                    "{:?} - create feature if a release is selected.\n",
-                    self.layout.create
-                )));
-                base.push(Text::raw(format!(
-                    "{:?} - create requirement if a feature is selected.\n",
-                    self.layout.create
-                )));
-            }
-            self.feature_text_formatted = Some(base);
-        }
+                Text::raw(format!(
+                    "{:?} - to close popups or stop a load\n",
+                    self.layout.escape
+                )),
+                Text::raw(format!(
+                    "{:?} - reload the list in focus from Aha\n",
+                    self.layout.refresh
+                )),
+                Text::raw(format!(
+                    "{:?} - search features, tab to switch product or all\n",
+                    self.layout.search
+                )),
+                Text::raw(format!(
+                    "{:?} - filter the current list\n",
+                    self.layout.filter
+                )),
+                Text::raw(format!(
+                    "{:?} - change the workflow status of the selected feature\n",
+                    self.layout.status
+                )),
+                Text::raw(format!(
+                    "{:?} - assign the selected feature or requirement\n",
+                    self.layout.assign
+                )),
+                Text::raw(format!(
+                    "{:?} - set the pull request of the selected feature, {:?} in the popup looks up the current git branch\n",
+                    self.layout.pull_request, self.layout.infer_pull_request
+                )),
+                Text::raw(format!(
+                    "{:?} - write release notes for the selected release\n",
+                    self.layout.release_notes
+                )),
+                Text::raw(format!(
+                    "{:?} {:?} - top and bottom of a list, a count like 5 jumps to that row\n",
+                    self.layout.top, self.layout.bottom
+                )),
+                Text::raw("a count before up or down moves that many rows and stops at the ends, 5j\n"),
+                Text::raw(format!(
+                    "{:?} - edit the description in $EDITOR, also works in the create popups\n",
+                    self.layout.external_editor
+                )),
+                Text::raw(format!(
+                    "{:?} - edit the name and description of the selected feature\n",
+                    self.layout.edit
+                )),
+                Text::raw(format!(
+                    "{:?} - comment on the shown feature or requirement\n",
+                    self.layout.comment
+                )),
+                Text::raw(format!(
+                    "{:?} - check off or add to-dos of the shown feature or requirement\n",
+                    self.layout.todos
+                )),
+                Text::raw(format!(
+                    "{:?} {:?} - next and previous feature, up and down scroll a shown feature\n",
+                    self.layout.next_feature, self.layout.previous_feature
+                )),
+                Text::raw(format!(
+                    "{:?} {:?} {:?} {:?} - scroll this pane by a page or half a page\n",
+                    self.layout.page_down,
+                    self.layout.page_up,
+                    self.layout.half_page_down,
+                    self.layout.half_page_up
+                )),
+            ];
+            for (name, keys) in self.layout.action_chords.iter() {
+                base.push(Text::raw(format!("{:?} - same as {}\n", keys, name)));
+            }
+            if self.active_layer != Screen::Project {
+                base.push(Text::raw("\nRelease Actions:\n"));
+                base.push(Text::raw(format!(
+                    "{:?} - create feature if a release is selected.\n",
+                    self.layout.create
+                )));
+                base.push(Text::raw(format!(
+                    "{:?} - create requirement if a feature is selected.\n",
+                    self.layout.create
+                )));
+            }
+            self.feature_text_formatted = Some(base);
+        }
     }
 
     pub fn advance(&mut self) {
+        self.worker.tick();
         let event = self.events.pop().unwrap();
         self.events.insert(0, event);
     }
@@ -371,109 +1182,104 @@ impl<'a> App<'a> {
                     if let Some(data) = self.feature_text_formatted.as_ref() {
                         data.clone()
                     } else {
-                        let feature = self.features.items[i].clone();
-                        if !feature.1.is_null() {
-                            let selected_feature =
-                                if feature.0.starts_with("└") || feature.0.starts_with("├") {
-                                    let clean_string = feature
-                                        .0
-                                        .splitn(2, ' ')
-                                        .collect::<Vec<_>>()
-                                        .last()
-                                        .unwrap()
-                                        .to_string();
-                                    let requirement = feature.1["requirements"]
-                                        .as_array()
-                                        .unwrap()
-                                        .iter()
-                                        .find({
-                                            |req| {
-                                                clean_string
-                                                    == format!(
-                                                        "{} - {}",
-                                                        req["name"].as_str().unwrap(),
-                                                        req["workflow_status"]["name"]
-                                                            .as_str()
-                                                            .unwrap(),
-                                                    )
-                                            }
-                                        })
-                                        .unwrap_or(&feature.1)
-                                        .clone();
-                                    self.feature_title = format!(
-                                        "Requirement {}",
-                                        requirement["reference_num"].as_str().unwrap()
-                                    );
-                                    requirement
-                                } else {
-                                    self.feature_title = format!(
-                                        "Feature {}",
-                                        feature.1["reference_num"].as_str().unwrap()
-                                    );
-                                    feature.1
-                                };
-                            self.feature_text = vec![selected_feature.to_string()];
-                            let rgb1 = RGBColor::from_hex_code(
-                                selected_feature["workflow_status"]["color"]
-                                    .as_str()
-                                    .unwrap(),
-                            )
-                            .unwrap()
-                            .int_rgb_tup();
-                            //self.debug_txt = format!("{:?}", rgb1);
-
-                            let html = selected_feature["description"]["body"]
-                                .as_str()
-                                .unwrap()
-                                .to_string();
-                            //self.debug_txt = format!("{:?} - {} - {}", rgb1, max_width, max_width - 9);
-                            let width = if max_width % 2 == 0 {
-                                max_width - 8
-                            } else {
-                                max_width - 9
-                            };
-
-                            let markdown = html2md::parse_html_custom(
-                                &html,
-                                &HashMap::default(),
-                                html2md::Config {
-                                    max_length: width,
-                                    new_line_break: "\n".to_string(),
-                                    logger: None,
-                                },
-                            );
-                            let result = vec![
-                                Text::raw(selected_feature["name"].as_str().unwrap().to_string()),
-                                Text::raw(" ["),
-                                Text::styled(
-                                    selected_feature["workflow_status"]["name"]
-                                        .as_str()
-                                        .unwrap()
-                                        .to_string(),
-                                    Style::default().bg(Color::Rgb(
-                                        rgb1.0 as u8,
-                                        rgb1.1 as u8,
-                                        rgb1.2 as u8,
-                                    )),
-                                ),
-                                Text::raw("]\n"),
-                                Text::raw(
-                                    selected_feature["assigned_to_user"]["name"]
-                                        .as_str()
-                                        .unwrap_or("Unassigned")
-                                        .to_string(),
-                                ),
-                                Text::raw("\n"),
-                                Text::raw(selected_feature["url"].as_str().unwrap().to_string()),
-                                Text::raw("\n"),
-                                Text::raw("\n"),
-                                Text::raw(markdown),
-                            ];
-                            self.feature_text_formatted = Some(result.clone());
-                            result
-                        } else {
-                            vec![]
+                        // a different feature starts at the top, a refreshed one keeps its place
+                        let id = self.features.items[i].1.id().map(String::from);
+                        if id != self.detail_id {
+                            self.detail_scroll = 0;
+                            self.detail_id = id;
                         }
+                        let (selected_feature, json) = match &self.features.items[i].1 {
+                            FeatureRow::Empty => return vec![],
+                            FeatureRow::Feature(feature) => {
+                                self.feature_title = format!("Feature {}", feature.reference_num);
+                                (
+                                    (
+                                        feature.name.clone(),
+                                        feature.workflow_status.clone().unwrap_or_default(),
+                                        feature.assigned_to_user.clone(),
+                                        feature.url.clone().unwrap_or_default(),
+                                        feature.description.body.clone(),
+                                        feature.pull_request(),
+                                    ),
+                                    serde_json::to_string(feature),
+                                )
+                            }
+                            FeatureRow::Requirement(_, requirement) => {
+                                self.feature_title =
+                                    format!("Requirement {}", requirement.reference_num);
+                                (
+                                    (
+                                        requirement.name.clone(),
+                                        requirement.workflow_status.clone().unwrap_or_default(),
+                                        requirement.assigned_to_user.clone(),
+                                        requirement.url.clone().unwrap_or_default(),
+                                        requirement.description.body.clone(),
+                                        requirement.pull_request(),
+                                    ),
+                                    serde_json::to_string(requirement),
+                                )
+                            }
+                        };
+                        let (name, status, assigned_to_user, url, html, pull_request) =
+                            selected_feature;
+                        self.feature_text = vec![json.unwrap_or_default()];
+                        let status_style = match status
+                            .color
+                            .as_ref()
+                            .and_then(|color| RGBColor::from_hex_code(color).ok())
+                        {
+                            Some(color) => {
+                                let rgb1 = color.int_rgb_tup();
+                                Style::default().bg(Color::Rgb(
+                                    rgb1.0 as u8,
+                                    rgb1.1 as u8,
+                                    rgb1.2 as u8,
+                                ))
+                            }
+                            None => Style::default(),
+                        };
+
+                        //self.debug_txt = format!("{:?} - {} - {}", rgb1, max_width, max_width - 9);
+                        let width = if max_width % 2 == 0 {
+                            max_width.saturating_sub(8)
+                        } else {
+                            max_width.saturating_sub(9)
+                        };
+
+                        let markdown = html2md::parse_html_custom(
+                            &html,
+                            &HashMap::default(),
+                            html2md::Config {
+                                max_length: width,
+                                new_line_break: "\n".to_string(),
+                                logger: None,
+                            },
+                        );
+                        let mut result = vec![
+                            Text::raw(name),
+                            Text::raw(" ["),
+                            Text::styled(status.name, status_style),
+                            Text::raw("]\n"),
+                            Text::raw(
+                                assigned_to_user
+                                    .map(|user| user.name)
+                                    .unwrap_or_else(|| "Unassigned".to_string()),
+                            ),
+                            Text::raw("\n"),
+                            Text::raw(url),
+                            Text::raw("\n"),
+                            Text::raw(
+                                pull_request
+                                    .map(|url| format!("PR {}\n", url))
+                                    .unwrap_or_default(),
+                            ),
+                            Text::raw("\n"),
+                            Text::raw(markdown),
+                        ];
+                        result.extend(self.todo_text());
+                        result.extend(self.comment_text(width));
+                        self.feature_text_formatted = Some(result.clone());
+                        result
                     }
                 }
                 None => vec![],
@@ -488,19 +1294,151 @@ impl<'a> App<'a> {
         }
     }
 
-    pub fn handle_search_popup(&mut self, event: Event<Key>, _aha: &Aha) -> Option<()> {
+    fn todo_text(&self) -> Vec<Text<'static>> {
+        let bold = Style::default().modifier(Modifier::BOLD);
+        let mut text = vec![Text::styled("\n\nTo-dos\n", bold)];
+        if self.worker.is_loading(Pane::Todos) {
+            text.push(Text::raw("loading\n"));
+        } else if self.todos.is_empty() {
+            text.push(Text::raw("none\n"));
+        }
+        for todo in self.todos.iter() {
+            let check = if todo.is_done() { "x" } else { " " };
+            text.push(Text::raw(format!("[{}] {}\n", check, todo.name)));
+        }
+        text
+    }
+
+    fn comment_text(&self, width: usize) -> Vec<Text<'static>> {
+        let bold = Style::default().modifier(Modifier::BOLD);
+        let mut text = vec![Text::styled("\nComments\n", bold)];
+        if self.worker.is_loading(Pane::Comments) {
+            text.push(Text::raw("loading\n"));
+        } else if self.comments.is_empty() {
+            text.push(Text::raw("none\n"));
+        } else {
+            let mut comments: Vec<&Comment> = self.comments.iter().collect();
+            comments.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+            push_comments(&mut text, &comments, None, 0, width);
+        }
+        text
+    }
+
+    pub fn search_title(&self) -> String {
+        let scope = match self.items.state.selected() {
+            Some(i) if !self.search_all => self.items.items[i].0.clone(),
+            _ => "All products".to_string(),
+        };
+        self.worker.title(
+            &format!("Search {} (tab to change scope)", scope),
+            Pane::Search,
+        )
+    }
+
+    pub fn search(&mut self, aha: &Aha) {
+        let product_id = match self.items.state.selected() {
+            Some(i) if !self.search_all => Some(self.items.items[i].1.id.clone()),
+            _ => None,
+        };
+        let request = aha.request::<Vec<Feature>>(
+            aha.search_url(&self.text_box, product_id),
+            "features".to_string(),
+        );
+        self.worker.run(Pane::Search, move |reporter| {
+            reporter.send(Response::Search(request.send()));
+        });
+    }
+
+    fn show_search_results(&mut self, features: Vec<Feature>) {
+        self.debug_txt = format!("{} results for {}", features.len(), self.text_box);
+        self.search_results = StatefulList::with_items(
+            features
+                .iter()
+                .map(|feature| {
+                    (
+                        format!("{} {}", feature.reference_num, feature.name),
+                        feature.clone(),
+                    )
+                })
+                .collect(),
+        );
+    }
+
+    pub fn open_search_result(&mut self, index: usize, aha: &Aha) {
+        let feature_id = self.search_results.items[index].1.id.clone();
+        let request =
+            aha.request::<Feature>(aha.json_url(feature_id, "feature"), "feature".to_string());
+        self.worker.run(Pane::Search, move |reporter| {
+            reporter.send(Response::SearchResult(request.send()));
+        });
+    }
+
+    // select the project, release and feature of a search result and show it
+    fn open_feature(&mut self, feature: Feature, aha: &Aha) {
+        let feature_id = feature.id.clone();
+        let release = feature.release.unwrap_or_default();
+        let release_id = release.id;
+        let project_id = release
+            .product_id
+            .or(feature.product_id)
+            .unwrap_or_default();
+
+        match self.items.items.iter().position(|x| x.1.id == project_id) {
+            Some(index) => {
+                self.items.state.select(Some(index));
+                self.want_release = Some(release_id);
+                self.want_feature = Some((feature_id.clone(), Screen::Feature));
+                self.load_releases(project_id, &aha);
+            }
+            None => {
+                self.debug_txt = format!("product {} not found", project_id);
+                return;
+            }
+        }
+        self.write_history("feature".to_string(), feature_id);
+        self.active_layer = Screen::Release;
+        self.feature_text_formatted = None;
+        self.popup = Popup::None;
+        self.text_box = "".to_string();
+        self.search_results = StatefulList::with_items(vec![]);
+    }
+
+    pub fn handle_search_popup(&mut self, event: Event<Key>, aha: &Aha) -> Option<()> {
         match event {
             Event::Input(input) => {
                 if input == self.layout.escape {
                     //hide
+                    self.worker.cancel(Pane::Search);
                     self.popup = Popup::None;
                 } else if input == Key::Char('\n') {
+                    match self.search_results.state.selected() {
+                        Some(i) => self.open_search_result(i, &aha),
+                        None => self.search(&aha),
+                    }
+                } else if input == Key::Char('\t') {
+                    self.search_all = !self.search_all;
+                    self.search_results.unselect();
+                } else if input == self.layout.down_arrow {
+                    if self.search_results.len() > 0 {
+                        self.search_results.next();
+                    }
+                } else if input == self.layout.up_arrow {
+                    if self.search_results.len() > 0 {
+                        self.search_results.previous();
+                    }
                 } else if input == Key::Backspace {
                     self.text_box.pop();
+                    self.search_results.unselect();
                 } else if let Key::Char(c) = input {
                     self.text_box.push(c);
+                    self.search_results.unselect();
                 }
             }
+            Event::Paste(text) => {
+                self.text_box.push_str(&text.replace('\n', " "));
+                self.search_results.unselect();
+            }
+            Event::Api(_) => {}
             Event::Tick => {
                 self.advance();
             }
@@ -516,60 +1454,55 @@ impl<'a> App<'a> {
                     self.popup = Popup::None;
                     self.new_requirement = RequirementCreate::new();
                     self.text_box_title = "Requirement Name".to_string();
-                } else if input == Key::Char('\n') {
+                    self.editor = Editor::single_line();
+                } else if input == self.layout.external_editor {
+                    self.external_edit = Some(ExternalEdit {
+                        target: EditTarget::Editor,
+                        text: self.editor.text(),
+                    });
+                } else if let EditorEvent::Submit = self.editor.handle_key(input) {
                     self.debug_txt = "enter".to_string();
-                    if let Some(title) = self.new_requirement.advance(self.text_box.to_string()) {
+                    if let Some(title) = self.new_requirement.advance(self.editor.text()) {
                         self.text_box_title = title.to_string();
-
-                        self.text_box = "".to_string();
+                        self.editor = editor_for(title);
                     } else {
                         self.debug_txt = "sending requirement".to_string();
                         self.popup = Popup::None;
-                        self.text_box = "".to_string();
+                        self.editor = Editor::single_line();
                         // send
                         let i = self.releases.state.selected().unwrap();
                         let project = self.releases.items[i].clone();
                         let i = self.features.state.selected().unwrap();
-                        let feature = self.features.items[i].clone();
-                        let feature_ref = feature.1["reference_num"].as_str().unwrap().to_string();
-
-                        match aha.send_requirement(feature_ref, &self.new_requirement) {
-                            Ok(x) => {
-                                self.debug_txt = format!(
-                                    "requirement created: {} {}",
-                                    x["requirement"]["reference_num"], x["requirement"]["url"]
-                                );
-
-                                self.load_features(
-                                    project.1["id"].as_str().unwrap().to_string(),
-                                    &aha,
-                                );
-                                let feature_id = x["requirement"]["id"].as_str().unwrap();
-
-                                if let Some(index) = self
-                                    .features
-                                    .items
-                                    .iter()
-                                    .position(|x| x.1["id"] == feature_id)
-                                {
-                                    self.features.state.select(Some(index));
-                                }
-                            }
-                            Err(_) => {
-                                self.debug_txt = "requirement error".to_string();
+                        let feature_ref = match self.features.items[i].1.feature() {
+                            Some(feature) => feature.reference_num.clone(),
+                            None => {
+                                self.debug_txt = "no feature selected".to_string();
+                                self.new_requirement = RequirementCreate::new();
+                                return Some(());
                             }
                         };
 
-                        self.new_requirement = RequirementCreate::new();
+                        let requirement =
+                            mem::replace(&mut self.new_requirement, RequirementCreate::new());
+                        let release_id = project.1.id.clone();
+                        let aha = aha.clone();
+                        self.worker.save(move |reporter| {
+                            let created = aha.send_requirement(feature_ref.clone(), &requirement);
+                            reporter.send(Response::RequirementCreated(
+                                release_id,
+                                feature_ref,
+                                created,
+                            ));
+                        });
+
                         self.text_box_title = "Requirement Name".to_string();
                     }
-                } else if input == Key::Backspace {
-                    self.text_box.pop();
-                } else if let Key::Char(c) = input {
-                    self.debug_txt = format!("char {}", c);
-                    self.text_box.push(c);
                 }
             }
+            Event::Paste(text) => {
+                self.editor.insert_str(&text);
+            }
+            Event::Api(_) => {}
             Event::Tick => {
                 self.advance();
             }
@@ -584,59 +1517,845 @@ impl<'a> App<'a> {
                     self.popup = Popup::None;
                     self.new_feature = FeatureCreate::new();
                     self.text_box_title = "Feature Name".to_string();
-                } else if input == Key::Char('\n') {
+                    self.editor = Editor::single_line();
+                } else if input == self.layout.external_editor {
+                    self.external_edit = Some(ExternalEdit {
+                        target: EditTarget::Editor,
+                        text: self.editor.text(),
+                    });
+                } else if let EditorEvent::Submit = self.editor.handle_key(input) {
                     self.debug_txt = "enter".to_string();
-                    if let Some(title) = self.new_feature.advance(self.text_box.to_string()) {
+                    if let Some(title) = self.new_feature.advance(self.editor.text()) {
                         self.text_box_title = title.to_string();
-
-                        self.text_box = "".to_string();
+                        self.editor = editor_for(title);
                     } else {
                         self.debug_txt = "sending feature".to_string();
                         self.popup = Popup::None;
-                        self.text_box = "".to_string();
+                        self.editor = Editor::single_line();
                         // send
                         let i = self.releases.state.selected().unwrap();
                         let project = self.releases.items[i].clone();
-                        self.new_feature.release_id = project.1["id"].as_str().unwrap().to_string();
+                        self.new_feature.release_id = project.1.id.clone();
                         // show debug with helpful data after create and select
-                        match aha.send_feature(&self.new_feature) {
-                            Ok(x) => {
-                                self.debug_txt = format!(
-                                    "feature created: {} {}",
-                                    x["feature"]["reference_num"], x["feature"]["url"]
-                                );
-
-                                self.load_features(
-                                    project.1["id"].as_str().unwrap().to_string(),
-                                    &aha,
-                                );
-                                let feature_id = x["feature"]["id"].as_str().unwrap();
-
-                                if let Some(index) = self
-                                    .features
-                                    .items
-                                    .iter()
-                                    .position(|x| x.1["id"] == feature_id)
-                                {
-                                    self.features.state.select(Some(index));
-                                }
-                            }
-                            Err(_) => {
-                                self.debug_txt = "feature error".to_string();
-                            }
-                        };
+                        let feature = mem::replace(&mut self.new_feature, FeatureCreate::new());
+                        let release_id = project.1.id.clone();
+                        let aha = aha.clone();
+                        self.worker.save(move |reporter| {
+                            let created = aha.send_feature(&feature);
+                            reporter.send(Response::FeatureCreated(release_id, created));
+                        });
 
-                        self.new_feature = FeatureCreate::new();
                         self.text_box_title = "Feature Name".to_string();
                     }
+                }
+            }
+            Event::Paste(text) => {
+                self.editor.insert_str(&text);
+            }
+
+            Event::Api(_) => {}
+            Event::Tick => {
+                self.advance();
+            }
+        }
+        //dont break from here
+        Some(())
+    }
+
+    pub fn enter_selected(&mut self, aha: &Aha) {
+        self.feature_text_formatted = None;
+        self.debug_txt = "over".to_string();
+        if self.active_layer == Screen::Features && self.features.state.selected().is_some() {
+            self.active_layer = Screen::Feature;
+        }
+        if self.active_layer == Screen::Release {
+            match self.releases.state.selected() {
+                Some(i) => {
+                    self.active_layer = Screen::Features;
+                    let release = self.releases.items[i].clone();
+
+                    self.load_features(release.1.id, &aha);
+                }
+                None => {}
+            };
+        }
+        if self.active_layer == Screen::Project {
+            match self.items.state.selected() {
+                Some(i) => {
+                    self.active_layer = Screen::Release;
+                    let project = self.items.items[i].clone();
+                    self.load_releases(project.1.id, &aha);
+                }
+                None => {}
+            };
+        }
+    }
+
+    fn active_names(&self) -> Vec<&str> {
+        match self.active_layer {
+            Screen::Project => self.items.items.iter().map(|i| i.0.as_str()).collect(),
+            Screen::Release => self.releases.items.iter().map(|i| i.0.as_str()).collect(),
+            _ => self.features.items.iter().map(|i| i.0.as_str()).collect(),
+        }
+    }
+
+    pub fn start_filter(&mut self) {
+        self.filter = FilteredList::new();
+        self.refresh_filter();
+        self.popup = Popup::Filter;
+    }
+
+    fn refresh_filter(&mut self) {
+        let names = self
+            .active_names()
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        self.filter.update(&names);
+    }
+
+    // one line per match with the matched characters highlighted
+    pub fn filter_lines(&self, selected_style: Style, match_style: Style) -> Vec<Text<'static>> {
+        let names = self.active_names();
+        let mut lines = vec![];
+        for (row, m) in self.filter.matches.iter().enumerate() {
+            let selected = self.filter.state.selected() == Some(row);
+            let base = if selected {
+                selected_style
+            } else {
+                Style::default()
+            };
+            lines.push(Text::styled(if selected { ">" } else { " " }, base));
+            for (i, c) in names[m.index].chars().enumerate() {
+                if m.positions.contains(&i) {
+                    lines.push(Text::styled(c.to_string(), match_style));
+                } else {
+                    lines.push(Text::styled(c.to_string(), base));
+                }
+            }
+            lines.push(Text::raw("\n"));
+        }
+        lines
+    }
+
+    pub fn handle_filter_popup(&mut self, event: Event<Key>, aha: &Aha) -> Option<()> {
+        match event {
+            Event::Input(input) => {
+                if input == self.layout.escape {
+                    self.popup = Popup::None;
+                } else if input == Key::Char('\n') {
+                    self.popup = Popup::None;
+                    if let Some(index) = self.filter.selected_index() {
+                        self.feature_text_formatted = None;
+                        match self.active_layer {
+                            Screen::Project => self.items.state.select(Some(index)),
+                            Screen::Release => self.releases.state.select(Some(index)),
+                            _ => self.features.state.select(Some(index)),
+                        }
+                        if self.active_layer == Screen::Feature {
+                            self.active_layer = Screen::Features;
+                        }
+                        self.enter_selected(&aha);
+                    }
+                } else if input == self.layout.down_arrow || input == Key::Ctrl('n') {
+                    self.filter.next();
+                } else if input == self.layout.up_arrow || input == Key::Ctrl('p') {
+                    self.filter.previous();
+                } else {
+                    if input == Key::Backspace {
+                        self.filter.query.pop();
+                    } else if let Key::Char(c) = input {
+                        self.filter.query.push(c);
+                    }
+                    self.refresh_filter();
+                }
+            }
+            Event::Paste(text) => {
+                self.filter.query.push_str(&text.replace('\n', " "));
+                self.refresh_filter();
+            }
+            Event::Api(_) => {}
+            Event::Tick => {
+                self.advance();
+            }
+        }
+        //dont break from here
+        Some(())
+    }
+
+    fn selected_row(&self) -> Option<FeatureRow> {
+        self.features
+            .state
+            .selected()
+            .and_then(|i| self.features.items.get(i))
+            .map(|row| row.1.clone())
+    }
+
+    fn selected_product_id(&self) -> Option<String> {
+        self.items
+            .state
+            .selected()
+            .map(|i| self.items.items[i].1.id.clone())
+    }
+
+    fn loaded_features(&self) -> Vec<Feature> {
+        self.features
+            .items
+            .iter()
+            .filter_map(|row| match &row.1 {
+                FeatureRow::Feature(feature) => Some(feature.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // offline and dry run creates never reach Aha, a reload would not bring them in
+    fn is_local(&self, release_id: &str, aha: &Aha) -> bool {
+        (aha.offline || aha.dry_run) && self.features_release.as_deref() == Some(release_id)
+    }
+
+    // adds a record that is only known here to the rows and selects it
+    fn add_local_row<F: FnOnce(&mut Vec<Feature>)>(&mut self, id: &str, add: F) {
+        let mut features = self.loaded_features();
+        add(&mut features);
+        self.features.items = feature_rows(&features);
+        let index = self
+            .features
+            .items
+            .iter()
+            .position(|row| row.1.id() == Some(id));
+        if index.is_some() {
+            self.features.state.select(index);
+        }
+        self.feature_text_formatted = None;
+    }
+
+    // rebuild the feature rows after changing the loaded features, keeping the selection
+    fn update_features<F: FnMut(&mut Feature)>(&mut self, mut change: F) {
+        let mut features = self.loaded_features();
+        if features.is_empty() {
+            return;
+        }
+        for feature in features.iter_mut() {
+            change(feature);
+        }
+        let selected = self.features.state.selected();
+        self.features.items = feature_rows(&features);
+        self.features.state.select(selected);
+        self.feature_text_formatted = None;
+    }
+
+    pub fn open_status_picker(&mut self, aha: &Aha) {
+        let current = match self.selected_row() {
+            Some(FeatureRow::Feature(feature)) => feature.workflow_status,
+            Some(FeatureRow::Requirement(_, requirement)) => requirement.workflow_status,
+            _ => {
+                self.debug_txt = "select a feature first".to_string();
+                return;
+            }
+        };
+        let product_id = match self.selected_product_id() {
+            Some(id) => id,
+            None => return,
+        };
+        let current_id = current.and_then(|status| status.id);
+        self.debug_txt = "loading workflows".to_string();
+        let aha = aha.clone();
+        self.worker.run(Pane::Picker, move |reporter| {
+            reporter.send(Response::Workflows(current_id, aha.workflows(product_id)));
+        });
+    }
+
+    // a picker that loaded after another popup opened is not shown
+    fn show_status_picker(&mut self, current_id: Option<String>, workflows: Vec<Workflow>) {
+        if self.popup != Popup::None {
+            return;
+        }
+        // only the statuses of the workflow the feature is in
+        let statuses: Vec<WorkflowStatus> = match workflows.iter().find(|workflow| {
+            workflow
+                .workflow_statuses
+                .iter()
+                .any(|status| status.id.is_some() && status.id == current_id)
+        }) {
+            Some(workflow) => workflow.workflow_statuses.clone(),
+            None => workflows
+                .iter()
+                .flat_map(|workflow| workflow.workflow_statuses.clone())
+                .collect(),
+        };
+        let selected = statuses
+            .iter()
+            .position(|status| status.id.is_some() && status.id == current_id);
+        self.picker = StatefulList::with_items(
+            statuses
+                .into_iter()
+                .map(|status| (status.name.clone(), status.name))
+                .collect(),
+        );
+        self.picker.state.select(selected);
+        self.picker_action = PickAction::Status;
+        self.popup = Popup::Pick;
+    }
+
+    pub fn pick_title(&self) -> String {
+        match self.picker_action {
+            PickAction::Status => "Workflow status".to_string(),
+            PickAction::Assign => "Assign to".to_string(),
+            PickAction::Todo => "To-dos - enter to check or uncheck".to_string(),
+        }
+    }
+
+    pub fn open_assign_picker(&mut self, aha: &Aha) {
+        if self
+            .selected_row()
+            .and_then(|row| row.id().map(String::from))
+            .is_none()
+        {
+            self.debug_txt = "select a feature first".to_string();
+            return;
+        }
+        let product_id = match self.selected_product_id() {
+            Some(id) => id,
+            None => return,
+        };
+        self.debug_txt = "loading users".to_string();
+        let aha = aha.clone();
+        self.worker.run(Pane::Picker, move |reporter| {
+            reporter.send(Response::Users(aha.users(product_id)));
+        });
+    }
+
+    fn show_assign_picker(&mut self, users: Vec<User>, aha: &Aha) {
+        if self.popup != Popup::None {
+            return;
+        }
+        let mut choices = vec![(format!("Me ({})", aha.user_email), aha.user_email.clone())];
+        choices.extend(users.into_iter().filter_map(|user| {
+            let name = user.name;
+            user.email
+                .map(|email| (format!("{} <{}>", name, email), email))
+        }));
+        self.picker = StatefulList::with_items(choices);
+        self.picker.state.select(Some(0));
+        self.picker_action = PickAction::Assign;
+        self.popup = Popup::Pick;
+    }
+
+    fn assign(&mut self, email: String, aha: &Aha) {
+        let update = FeatureUpdate {
+            assigned_to_user: Some(email.clone()),
+            ..FeatureUpdate::new()
+        };
+        let row = match self.selected_row() {
+            Some(row) if row.id().is_some() => row,
+            _ => return,
+        };
+        self.debug_txt = format!("assigning to {}", email);
+        let aha = aha.clone();
+        self.worker.save(move |reporter| {
+            let (id, result) = match row {
+                FeatureRow::Requirement(_, requirement) => (
+                    requirement.id.clone(),
+                    aha.update_requirement(requirement.id, &update)
+                        .map(|requirement| requirement.assigned_to_user),
+                ),
+                FeatureRow::Feature(feature) => (
+                    feature.id.clone(),
+                    aha.update_feature(feature.id, &update)
+                        .map(|feature| feature.assigned_to_user),
+                ),
+                FeatureRow::Empty => return,
+            };
+            reporter.send(Response::Assigned(id, email, result));
+        });
+    }
+
+    fn assigned(&mut self, id: String, email: String, user: Option<User>, aha: &Aha) {
+        let user = user.unwrap_or(User {
+            name: email.clone(),
+            email: Some(email),
+            ..User::default()
+        });
+        self.update_features(|feature| {
+            if feature.id == id {
+                feature.assigned_to_user = Some(user.clone());
+            }
+            for requirement in feature.requirements.iter_mut() {
+                if requirement.id == id {
+                    requirement.assigned_to_user = Some(user.clone());
+                }
+            }
+        });
+        self.debug_txt = format!("assigned to {}", user.name);
+        self.reload_features(id, aha);
+    }
+
+    pub fn open_pull_request_popup(&mut self) {
+        let current = match self.selected_row() {
+            Some(FeatureRow::Feature(feature)) => feature.pull_request(),
+            Some(FeatureRow::Requirement(_, requirement)) => requirement.pull_request(),
+            _ => {
+                self.debug_txt = "select a feature first".to_string();
+                return;
+            }
+        };
+        self.text_box = current.unwrap_or_default();
+        self.popup = Popup::PullRequest;
+    }
+
+    // asks git and github for the pull request of the current branch
+    fn infer_pull_request(&mut self) {
+        self.debug_txt = "looking up the pull request of the current branch".to_string();
+        self.worker.run(Pane::Picker, move |reporter| {
+            reporter.send(Response::PullRequestFound(github::infer_pull_request_url()));
+        });
+    }
+
+    fn set_pull_request(&mut self, url: String, aha: &Aha) {
+        let (id, reference) = match self.selected_row() {
+            Some(FeatureRow::Feature(feature)) => (feature.id, feature.reference_num),
+            Some(FeatureRow::Requirement(_, requirement)) => {
+                (requirement.id, requirement.reference_num)
+            }
+            _ => return,
+        };
+        self.debug_txt = format!("setting the {} pull request", reference);
+        let aha = aha.clone();
+        self.worker.save(move |reporter| {
+            let result = cli::set_pull_request(&aha, reference.clone(), url.clone()).map(|_| ());
+            reporter.send(Response::PullRequest(id, reference, url, result));
+        });
+    }
+
+    fn pull_request_set(&mut self, id: String, reference: String, url: String, aha: &Aha) {
+        let field = CustomField {
+            key: "pull_request".to_string(),
+            name: None,
+            value: Value::String(url.clone()),
+        };
+        let set_field = |custom_fields: &mut Vec<CustomField>| {
+            custom_fields.retain(|field| field.key != "pull_request");
+            custom_fields.push(field.clone());
+        };
+        self.update_features(|feature| {
+            if feature.id == id {
+                set_field(&mut feature.custom_fields);
+            }
+            for requirement in feature.requirements.iter_mut() {
+                if requirement.id == id {
+                    set_field(&mut requirement.custom_fields);
+                }
+            }
+        });
+        self.debug_txt = format!("{} pull request set to {}", reference, url);
+        self.reload_features(id, aha);
+    }
+
+    fn open_edit(&mut self) {
+        let feature = match self.selected_row() {
+            Some(FeatureRow::Feature(feature)) => feature,
+            _ => {
+                self.debug_txt = "select a feature first".to_string();
+                return;
+            }
+        };
+        self.editor = Editor::single_line();
+        self.editor.set_text(&feature.name);
+        self.text_box_title = format!("{} Name", feature.reference_num);
+        self.feature_edit = Some(FeatureEdit {
+            id: feature.id,
+            reference_num: feature.reference_num,
+            name: feature.name,
+            description: html2md::parse_html(&feature.description.body)
+                .trim()
+                .to_string(),
+            new_name: None,
+            new_description: None,
+        });
+        self.popup = Popup::Edit;
+    }
+
+    pub fn handle_edit_popup(&mut self, event: Event<Key>) -> Option<()> {
+        match event {
+            Event::Input(input) => {
+                if input == self.layout.escape {
+                    self.popup = Popup::None;
+                    self.feature_edit = None;
+                    self.editor = Editor::single_line();
+                } else if input == self.layout.external_editor {
+                    self.external_edit = Some(ExternalEdit {
+                        target: EditTarget::Editor,
+                        text: self.editor.text(),
+                    });
+                } else if let EditorEvent::Submit = self.editor.handle_key(input) {
+                    let text = self.editor.text();
+                    let edit = match self.feature_edit.as_mut() {
+                        Some(edit) => edit,
+                        None => {
+                            self.popup = Popup::None;
+                            return Some(());
+                        }
+                    };
+                    if edit.new_name.is_none() {
+                        edit.new_name = Some(text);
+                        self.editor = Editor::multiline();
+                        self.editor.set_text(&edit.description);
+                        self.text_box_title = format!("{} Description", edit.reference_num);
+                    } else {
+                        edit.new_description = Some(text);
+                        let update = edit.update();
+                        if update.name.is_none() && update.description.is_none() {
+                            self.debug_txt = "nothing changed".to_string();
+                            self.popup = Popup::None;
+                            self.feature_edit = None;
+                        } else {
+                            self.diff_scroll = 0;
+                            self.popup = Popup::Diff;
+                        }
+                    }
+                }
+            }
+            Event::Paste(text) => {
+                self.editor.insert_str(&text);
+            }
+            Event::Api(_) => {}
+            Event::Tick => {
+                self.advance();
+            }
+        }
+        //dont break from here
+        Some(())
+    }
+
+    pub fn diff_title(&self) -> String {
+        match self.feature_edit {
+            Some(ref edit) => format!("Save {}? enter to save, esc to cancel", edit.reference_num),
+            None => "Save".to_string(),
+        }
+    }
+
+    pub fn diff_text(&self) -> Vec<Text<'static>> {
+        let edit = match self.feature_edit {
+            Some(ref edit) => edit,
+            None => return vec![],
+        };
+        let removed = Style::default().fg(Color::Red);
+        let added = Style::default().fg(Color::Green);
+        let mut text = vec![];
+        if let Some(ref name) = edit.new_name {
+            if *name != edit.name {
+                text.push(Text::raw("Name\n"));
+                text.push(Text::styled(format!("- {}\n", edit.name), removed));
+                text.push(Text::styled(format!("+ {}\n", name), added));
+                text.push(Text::raw("\n"));
+            }
+        }
+        if let Some(ref description) = edit.new_description {
+            if *description != edit.description {
+                text.push(Text::raw("Description\n"));
+                for change in diff::diff_lines(&edit.description, description) {
+                    text.push(match change {
+                        Change::Same(line) => Text::raw(format!("  {}\n", line)),
+                        Change::Removed(line) => Text::styled(format!("- {}\n", line), removed),
+                        Change::Added(line) => Text::styled(format!("+ {}\n", line), added),
+                    });
+                }
+            }
+        }
+        text
+    }
+
+    fn save_edit(&mut self, aha: &Aha) {
+        let edit = match self.feature_edit.take() {
+            Some(edit) => edit,
+            None => return,
+        };
+        self.debug_txt = format!("saving {}", edit.reference_num);
+        let aha = aha.clone();
+        self.worker.save(move |reporter| {
+            let result = aha.update_feature(edit.id.clone(), &edit.update());
+            reporter.send(Response::Edited(edit.id, edit.reference_num, result));
+        });
+    }
+
+    fn edited(&mut self, id: String, reference_num: String, updated: Feature, aha: &Aha) {
+        self.update_features(|feature| {
+            if feature.id == id {
+                feature.name = updated.name.clone();
+                feature.description = updated.description.clone();
+            }
+        });
+        self.debug_txt = format!("{} updated", reference_num);
+        self.reload_features(id, aha);
+    }
+
+    // fetched again after a save so the saved copy of the list is not left with the old
+    // rows. offline and dry run writes never reached Aha, the rows changed here are all
+    // there is
+    fn reload_features(&mut self, id: String, aha: &Aha) {
+        if aha.offline || aha.dry_run {
+            return;
+        }
+        if let Some(release_id) = self.features_release.clone() {
+            self.want_feature = Some((id, self.active_layer.clone()));
+            self.fetch_features(release_id, aha, Lookup::Refresh);
+        }
+    }
+
+    pub fn handle_diff_popup(&mut self, event: Event<Key>, aha: &Aha) -> Option<()> {
+        match event {
+            Event::Input(input) => {
+                if input == self.layout.escape {
+                    self.popup = Popup::None;
+                    self.feature_edit = None;
+                    self.debug_txt = "edit canceled".to_string();
+                } else if input == Key::Char('\n') {
+                    self.popup = Popup::None;
+                    self.save_edit(&aha);
+                } else if input == self.layout.down || input == self.layout.down_arrow {
+                    self.diff_scroll = self.diff_scroll.saturating_add(1);
+                } else if input == self.layout.up || input == self.layout.up_arrow {
+                    self.diff_scroll = self.diff_scroll.saturating_sub(1);
+                }
+            }
+            Event::Paste(_) => {}
+            Event::Api(_) => {}
+            Event::Tick => {
+                self.advance();
+            }
+        }
+        //dont break from here
+        Some(())
+    }
+
+    pub fn handle_pull_request_popup(&mut self, event: Event<Key>, aha: &Aha) -> Option<()> {
+        match event {
+            Event::Input(input) => {
+                if input == self.layout.escape {
+                    self.worker.cancel(Pane::Picker);
+                    self.popup = Popup::None;
+                    self.text_box = "".to_string();
+                } else if input == self.layout.infer_pull_request {
+                    self.infer_pull_request();
+                } else if input == Key::Char('\n') {
+                    self.worker.cancel(Pane::Picker);
+                    self.popup = Popup::None;
+                    let url = self.text_box.trim().to_string();
+                    self.text_box = "".to_string();
+                    if !url.is_empty() {
+                        self.set_pull_request(url, &aha);
+                    }
                 } else if input == Key::Backspace {
                     self.text_box.pop();
                 } else if let Key::Char(c) = input {
-                    self.debug_txt = format!("char {}", c);
                     self.text_box.push(c);
                 }
             }
+            Event::Paste(text) => {
+                self.text_box.push_str(text.trim());
+            }
+            Event::Api(_) => {}
+            Event::Tick => {
+                self.advance();
+            }
+        }
+        //dont break from here
+        Some(())
+    }
+
+    // writes markdown next to where the cli was started
+    fn write_release_notes(&mut self, aha: &Aha) {
+        let release = match self.releases.state.selected() {
+            Some(i) => self.releases.items[i].1.clone(),
+            None => {
+                self.debug_txt = "select a release first".to_string();
+                return;
+            }
+        };
+        self.debug_txt = "loading release notes".to_string();
+        let aha = aha.clone();
+        self.worker.save(move |reporter| {
+            let features = aha.features(release.id.clone());
+            reporter.send(Response::ReleaseNotes(release, features));
+        });
+    }
 
+    fn save_release_notes(&mut self, release: Release, features: Vec<Feature>) {
+        let notes = release_notes::markdown(&release, &features, GroupBy::Status);
+        let path_name = format!(
+            "{}-release-notes.md",
+            release.reference_num.clone().unwrap_or(release.id.clone())
+        );
+        match std::fs::write(&path_name, notes) {
+            Ok(_) => {
+                self.debug_txt = format!("release notes written to {}", path_name);
+            }
+            Err(why) => {
+                self.debug_txt = format!("couldn't write to {}: {}", path_name, why);
+            }
+        }
+    }
+
+    fn open_description_editor(&mut self) {
+        let body = match self.selected_row() {
+            Some(FeatureRow::Feature(feature)) => feature.description.body,
+            Some(FeatureRow::Requirement(_, requirement)) => requirement.description.body,
+            _ => {
+                self.debug_txt = "select a feature first".to_string();
+                return;
+            }
+        };
+        self.external_edit = Some(ExternalEdit {
+            target: EditTarget::Description,
+            text: html2md::parse_html(&body).trim().to_string(),
+        });
+    }
+
+    pub fn finish_external_edit(
+        &mut self,
+        edit: ExternalEdit,
+        result: Result<String, failure::Error>,
+        aha: &Aha,
+    ) {
+        let text = match result {
+            Ok(text) => text,
+            Err(e) => {
+                self.debug_txt = format!("editor error: {}", e);
+                return;
+            }
+        };
+        match edit.target {
+            EditTarget::Editor => self.editor.set_text(&text),
+            EditTarget::Description => {
+                if text == edit.text {
+                    self.debug_txt = "description unchanged".to_string();
+                    return;
+                }
+                self.set_description(text, aha);
+            }
+        }
+    }
+
+    fn set_description(&mut self, text: String, aha: &Aha) {
+        let update = FeatureUpdate {
+            description: Some(text),
+            ..FeatureUpdate::new()
+        };
+        let row = match self.selected_row() {
+            Some(row) if row.id().is_some() => row,
+            _ => return,
+        };
+        self.debug_txt = "saving the description".to_string();
+        let aha = aha.clone();
+        self.worker.save(move |reporter| {
+            let (id, result) = match row {
+                FeatureRow::Requirement(_, requirement) => (
+                    requirement.id.clone(),
+                    aha.update_requirement(requirement.id, &update)
+                        .map(|requirement| requirement.description),
+                ),
+                FeatureRow::Feature(feature) => (
+                    feature.id.clone(),
+                    aha.update_feature(feature.id, &update)
+                        .map(|feature| feature.description),
+                ),
+                FeatureRow::Empty => return,
+            };
+            reporter.send(Response::Description(id, result));
+        });
+    }
+
+    fn description_set(&mut self, id: String, description: Description, aha: &Aha) {
+        self.update_features(|feature| {
+            if feature.id == id {
+                feature.description = description.clone();
+            }
+            for requirement in feature.requirements.iter_mut() {
+                if requirement.id == id {
+                    requirement.description = description.clone();
+                }
+            }
+        });
+        self.debug_txt = "description updated".to_string();
+        self.reload_features(id, aha);
+    }
+
+    fn set_status(&mut self, name: String, aha: &Aha) {
+        let update = FeatureUpdate {
+            workflow_status: Some(WorkflowStatusUpdate { name: name.clone() }),
+            ..FeatureUpdate::new()
+        };
+        let row = match self.selected_row() {
+            Some(row) if row.id().is_some() => row,
+            _ => return,
+        };
+        self.debug_txt = format!("setting status {}", name);
+        let aha = aha.clone();
+        self.worker.save(move |reporter| {
+            let (id, result) = match row {
+                FeatureRow::Requirement(_, requirement) => (
+                    requirement.id.clone(),
+                    aha.update_requirement(requirement.id, &update)
+                        .map(|requirement| requirement.workflow_status),
+                ),
+                FeatureRow::Feature(feature) => (
+                    feature.id.clone(),
+                    aha.update_feature(feature.id, &update)
+                        .map(|feature| feature.workflow_status),
+                ),
+                FeatureRow::Empty => return,
+            };
+            reporter.send(Response::Status(id, name, result));
+        });
+    }
+
+    fn status_set(&mut self, id: String, name: String, status: Option<WorkflowStatus>, aha: &Aha) {
+        let status = status.unwrap_or(WorkflowStatus {
+            name: name.clone(),
+            ..WorkflowStatus::default()
+        });
+        self.update_features(|feature| {
+            if feature.id == id {
+                feature.workflow_status = Some(status.clone());
+            }
+            for requirement in feature.requirements.iter_mut() {
+                if requirement.id == id {
+                    requirement.workflow_status = Some(status.clone());
+                }
+            }
+        });
+        self.debug_txt = format!("status set to {}", name);
+        self.reload_features(id, aha);
+    }
+
+    pub fn handle_pick_popup(&mut self, event: Event<Key>, aha: &Aha) -> Option<()> {
+        match event {
+            Event::Input(input) => {
+                if input == self.layout.escape {
+                    self.popup = Popup::None;
+                } else if input == Key::Char('\n') {
+                    self.popup = Popup::None;
+                    if let Some(i) = self.picker.state.selected() {
+                        let value = self.picker.items[i].1.clone();
+                        match self.picker_action {
+                            PickAction::Status => self.set_status(value, &aha),
+                            PickAction::Assign => self.assign(value, &aha),
+                            PickAction::Todo => self.pick_todo(value, &aha),
+                        }
+                    }
+                } else if input == self.layout.down || input == self.layout.down_arrow {
+                    if self.picker.len() > 0 {
+                        self.picker.next();
+                    }
+                } else if input == self.layout.up || input == self.layout.up_arrow {
+                    if self.picker.len() > 0 {
+                        self.picker.previous();
+                    }
+                }
+            }
+            Event::Paste(_) => {}
+            Event::Api(_) => {}
             Event::Tick => {
                 self.advance();
             }
@@ -645,18 +2364,128 @@ impl<'a> App<'a> {
         Some(())
     }
 
+    fn max_detail_scroll(&self) -> u16 {
+        self.detail_lines.saturating_sub(self.detail_height)
+    }
+
+    pub fn scroll_detail(&mut self, lines: i32) {
+        let scroll = (self.detail_scroll as i32 + lines).max(0) as u16;
+        self.detail_scroll = scroll.min(self.max_detail_scroll());
+    }
+
+    // called after drawing once the wrapped size of the pane is known
+    pub fn set_detail_size(&mut self, lines: u16, height: u16) {
+        self.detail_lines = lines;
+        self.detail_height = height;
+        self.detail_scroll = self.detail_scroll.min(self.max_detail_scroll());
+    }
+
+    // gg or 5gg style jumps in the active list, a count picks the row
+    fn jump(&mut self, top: bool, count: Option<usize>) {
+        // the detail pane is the list on the feature screen
+        if self.active_layer == Screen::Feature {
+            self.detail_scroll = if top { 0 } else { self.max_detail_scroll() };
+            return;
+        }
+        self.feature_text_formatted = None;
+        let len = match self.active_layer {
+            Screen::Project => self.items.len(),
+            Screen::Release => self.releases.len(),
+            _ => self.features.len(),
+        };
+        let index = match count {
+            Some(count) => count.saturating_sub(1),
+            None if top => 0,
+            None => len.saturating_sub(1),
+        };
+        match self.active_layer {
+            Screen::Project => self.items.select(index),
+            Screen::Release => self.releases.select(index),
+            _ => self.features.select(index),
+        }
+        self.debug_txt = if top { "top" } else { "bottom" }.to_string();
+    }
+
     pub fn handle_nav(&mut self, event: Event<Key>, aha: &Aha) -> Option<()> {
         match event {
-            Event::Input(input) => {
+            Event::Input(key) => {
+                let (input, count) = match self.sequence.push(key, &self.layout) {
+                    Sequence::Pending => return Some(()),
+                    Sequence::Chord(name, count) if name == "top" || name == "bottom" => {
+                        self.jump(name == "top", count);
+                        return Some(());
+                    }
+                    // other chords act like the key of their action
+                    Sequence::Chord(name, count) => match self.layout.key_for(name) {
+                        Some(key) => (key, count.unwrap_or(1)),
+                        None => return Some(()),
+                    },
+                    Sequence::Key(input, count) => (input, count),
+                };
                 if input == self.layout.quit {
                     self.debug_txt = "q exit".to_string();
                     None
+                } else if input == self.layout.escape && self.cancel_loading() {
+                    Some(())
+                } else if input == self.layout.refresh {
+                    self.debug_txt = "refresh".to_string();
+                    self.refresh(&aha);
+                    Some(())
                 } else if input == self.layout.search {
                     self.debug_txt = "search".to_string();
                     self.popup = Popup::Search;
                     Some(())
+                } else if input == self.layout.filter {
+                    self.debug_txt = "filter".to_string();
+                    self.start_filter();
+                    Some(())
+                } else if input == self.layout.status
+                    && (self.active_layer == Screen::Features
+                        || self.active_layer == Screen::Feature)
+                {
+                    self.debug_txt = "status".to_string();
+                    self.open_status_picker(&aha);
+                    Some(())
+                } else if input == self.layout.assign
+                    && (self.active_layer == Screen::Features
+                        || self.active_layer == Screen::Feature)
+                {
+                    self.debug_txt = "assign".to_string();
+                    self.open_assign_picker(&aha);
+                    Some(())
+                } else if input == self.layout.pull_request
+                    && (self.active_layer == Screen::Features
+                        || self.active_layer == Screen::Feature)
+                {
+                    self.debug_txt = "pull request".to_string();
+                    self.open_pull_request_popup();
+                    Some(())
+                } else if input == self.layout.external_editor
+                    && self.active_layer == Screen::Feature
+                {
+                    self.debug_txt = "editor".to_string();
+                    self.open_description_editor();
+                    Some(())
+                } else if input == self.layout.todos && self.active_layer == Screen::Feature {
+                    self.debug_txt = "to-dos".to_string();
+                    self.open_todo_picker();
+                    Some(())
+                } else if input == self.layout.comment && self.active_layer == Screen::Feature {
+                    self.debug_txt = "comment".to_string();
+                    self.open_comment();
+                    Some(())
+                } else if input == self.layout.edit && self.active_layer == Screen::Feature {
+                    self.debug_txt = "edit".to_string();
+                    self.open_edit();
+                    Some(())
+                } else if input == self.layout.release_notes && self.active_layer != Screen::Project
+                {
+                    self.debug_txt = "release notes".to_string();
+                    self.write_release_notes(&aha);
+                    Some(())
                 } else if input == self.layout.create {
                     self.debug_txt = "create".to_string();
+                    self.editor = Editor::single_line();
                     if self.active_layer == Screen::Feature {
                         self.text_box_title = "Requirement Name".to_string();
                     } else {
@@ -681,54 +2510,53 @@ impl<'a> App<'a> {
                     if self.active_layer == Screen::Feature {
                         self.active_layer = Screen::Features;
                     }
+                    self.detail_scroll = 0;
 
+                    Some(())
+                } else if input == self.layout.page_down {
+                    self.scroll_detail(self.detail_height as i32 * count as i32);
+                    Some(())
+                } else if input == self.layout.page_up {
+                    self.scroll_detail(-(self.detail_height as i32) * count as i32);
+                    Some(())
+                } else if input == self.layout.half_page_down {
+                    self.scroll_detail(self.detail_height as i32 / 2 * count as i32);
+                    Some(())
+                } else if input == self.layout.half_page_up {
+                    self.scroll_detail(-(self.detail_height as i32 / 2) * count as i32);
+                    Some(())
+                } else if (input == self.layout.next_feature
+                    || input == self.layout.previous_feature)
+                    && (self.active_layer == Screen::Features
+                        || self.active_layer == Screen::Feature)
+                {
+                    self.feature_text_formatted = None;
+                    self.features.step(count, input == self.layout.next_feature);
+                    Some(())
+                } else if (input == self.layout.down || input == self.layout.down_arrow)
+                    && self.active_layer == Screen::Feature
+                {
+                    self.scroll_detail(count as i32);
+                    Some(())
+                } else if (input == self.layout.up || input == self.layout.up_arrow)
+                    && self.active_layer == Screen::Feature
+                {
+                    self.scroll_detail(-(count as i32));
                     Some(())
                 } else if input == self.layout.right
                     || input == self.layout.right_arrow
                     || input == self.layout.right_alt
                 {
-                    self.feature_text_formatted = None;
-                    self.debug_txt = "over".to_string();
-                    if self.active_layer == Screen::Features && self.features.state.selected().is_some() {
-                        self.active_layer = Screen::Feature;
-                    }
-                    if self.active_layer == Screen::Release {
-                        match self.releases.state.selected() {
-                            Some(i) => {
-                                self.active_layer = Screen::Features;
-                                let release = self.releases.items[i].clone();
-
-                                self.load_features(
-                                    release.1["id"].as_str().unwrap().to_string(),
-                                    &aha,
-                                );
-                            }
-                            None => {}
-                        };
-                    }
-                    if self.active_layer == Screen::Project {
-                        match self.items.state.selected() {
-                            Some(i) => {
-                                self.active_layer = Screen::Release;
-                                let project = self.items.items[i].clone();
-                                self.load_releases(
-                                    project.1["id"].as_str().unwrap().to_string(),
-                                    &aha,
-                                );
-                            }
-                            None => {}
-                        };
-                    }
-
+                    self.enter_selected(&aha);
                     Some(())
                 } else if input == self.layout.down || input == self.layout.down_arrow {
                     self.feature_text_formatted = None;
                     self.debug_txt = "down".to_string();
                     match self.active_layer {
-                        Screen::Project => self.items.next(),
-                        Screen::Release => self.releases.next(),
-                        Screen::Features => self.features.next(),
-                        Screen::Feature => self.features.next(),
+                        Screen::Project => self.items.step(count, true),
+                        Screen::Release => self.releases.step(count, true),
+                        Screen::Features => self.features.step(count, true),
+                        Screen::Feature => self.features.step(count, true),
                         _ => {}
                     }
 
@@ -737,10 +2565,10 @@ impl<'a> App<'a> {
                     self.feature_text_formatted = None;
                     self.debug_txt = "up".to_string();
                     match self.active_layer {
-                        Screen::Project => self.items.previous(),
-                        Screen::Release => self.releases.previous(),
-                        Screen::Features => self.features.previous(),
-                        Screen::Feature => self.features.previous(),
+                        Screen::Project => self.items.step(count, false),
+                        Screen::Release => self.releases.step(count, false),
+                        Screen::Features => self.features.step(count, false),
+                        Screen::Feature => self.features.step(count, false),
                         _ => {}
                     }
 
@@ -751,10 +2579,318 @@ impl<'a> App<'a> {
                 }
             }
 
+            Event::Paste(_) => Some(()),
+            Event::Api(_) => Some(()),
             Event::Tick => {
+                self.sequence.expire();
                 self.advance();
                 Some(())
             }
         }
     }
 }
+
+// chords are key names separated by spaces, "g g"
+fn parse_chord(name: &str, input: &str) -> Result<Vec<Key>, failure::Error> {
+    let mut parsed = vec![];
+    for part in input.split_whitespace() {
+        match App::get_key_from(part) {
+            Ok(found) => parsed.push(found),
+            Err(e) => bail!("{}: {}", name, e),
+        }
+    }
+    if parsed.is_empty() {
+        bail!("{}: no keys given", name);
+    }
+    Ok(parsed)
+}
+
+// overrides the bindings named in a layout file, bad names and conflicts are errors
+fn apply_layout(layout: &mut KeyLayout, file: &str) -> Result<(), failure::Error> {
+    let value: Layout = toml::from_str(file)?;
+    let bindings = vec![
+        ("up", value.up, &mut layout.up),
+        ("down", value.down, &mut layout.down),
+        ("left", value.left, &mut layout.left),
+        ("right", value.right, &mut layout.right),
+        ("up_arrow", value.up_arrow, &mut layout.up_arrow),
+        ("down_arrow", value.down_arrow, &mut layout.down_arrow),
+        ("left_arrow", value.left_arrow, &mut layout.left_arrow),
+        ("right_arrow", value.right_arrow, &mut layout.right_arrow),
+        ("right_alt", value.right_alt, &mut layout.right_alt),
+        ("escape", value.escape, &mut layout.escape),
+        ("quit", value.quit, &mut layout.quit),
+        ("search", value.search, &mut layout.search),
+        ("create", value.create, &mut layout.create),
+        ("filter", value.filter, &mut layout.filter),
+        ("status", value.status, &mut layout.status),
+        ("assign", value.assign, &mut layout.assign),
+        ("pull_request", value.pull_request, &mut layout.pull_request),
+        (
+            "infer_pull_request",
+            value.infer_pull_request,
+            &mut layout.infer_pull_request,
+        ),
+        (
+            "release_notes",
+            value.release_notes,
+            &mut layout.release_notes,
+        ),
+        (
+            "external_editor",
+            value.external_editor,
+            &mut layout.external_editor,
+        ),
+        ("edit", value.edit, &mut layout.edit),
+        ("refresh", value.refresh, &mut layout.refresh),
+        ("comment", value.comment, &mut layout.comment),
+        ("todos", value.todos, &mut layout.todos),
+        ("next_feature", value.next_feature, &mut layout.next_feature),
+        (
+            "previous_feature",
+            value.previous_feature,
+            &mut layout.previous_feature,
+        ),
+        ("page_down", value.page_down, &mut layout.page_down),
+        ("page_up", value.page_up, &mut layout.page_up),
+        (
+            "half_page_down",
+            value.half_page_down,
+            &mut layout.half_page_down,
+        ),
+        ("half_page_up", value.half_page_up, &mut layout.half_page_up),
+    ];
+    for (name, input, key) in bindings {
+        if let Some(input) = input {
+            *key = match App::get_key_from(&input) {
+                Ok(found) => found,
+                Err(e) => bail!("{}: {}", name, e),
+            };
+        }
+    }
+    if let Some(tick_rate) = value.tick_rate {
+        if tick_rate == 0 {
+            bail!("tick_rate must be more than 0");
+        }
+        layout.tick_rate = Duration::from_millis(tick_rate);
+    }
+    let chords = vec![
+        ("top", value.top, &mut layout.top),
+        ("bottom", value.bottom, &mut layout.bottom),
+    ];
+    for (name, input, keys) in chords {
+        if let Some(input) = input {
+            *keys = parse_chord(name, &input)?;
+        }
+    }
+    if let Some(chords) = value.chords {
+        let mut action_chords = vec![];
+        for (name, input) in chords {
+            let action = match layout
+                .bindings()
+                .iter()
+                .find(|(bound, _, _)| *bound == name)
+            {
+                Some((bound, _, _)) => *bound,
+                None => bail!("chords: unknown action {}", name),
+            };
+            action_chords.push((action, parse_chord(action, &input)?));
+        }
+        action_chords.sort_by_key(|(name, _)| *name);
+        layout.action_chords = action_chords;
+    }
+    layout.check_conflicts()
+}
+
+// descriptions get several lines, the other create fields are one line
+fn editor_for(title: &str) -> Editor {
+    if title == "Description" {
+        Editor::multiline()
+    } else {
+        Editor::single_line()
+    }
+}
+
+// a row per feature followed by a row per requirement
+fn feature_rows(feature_list: &[Feature]) -> Vec<(String, FeatureRow)> {
+    feature_list
+        .iter()
+        .map(|feature| {
+            let mut vec = vec![];
+
+            vec.push((
+                format!("{} - {}", feature.name, feature.status_name()),
+                FeatureRow::Feature(feature.clone()),
+            ));
+            let last = feature.requirements.len();
+            for (i, req) in feature.requirements.iter().enumerate() {
+                let brace = if i == last - 1 { "└" } else { "├" };
+                vec.push((
+                    format!("{} {} - {}", brace, req.name, req.status_name()),
+                    FeatureRow::Requirement(feature.clone(), req.clone()),
+                ))
+            }
+            vec
+        })
+        .flatten()
+        .collect()
+}
+
+// false once the job should stop, after an error or a cancel
+fn send_feature_pages(pages: &mut Pages<Feature>, reporter: &Reporter) -> bool {
+    let mut first = true;
+    for page in pages {
+        let failed = page.is_err();
+        let response = if first {
+            Response::FirstFeatures(page)
+        } else {
+            Response::Features(page)
+        };
+        first = false;
+        if !reporter.send(response) || failed {
+            return false;
+        }
+    }
+    true
+}
+
+fn comment_date(created_at: &str) -> String {
+    match chrono::DateTime::parse_from_rfc3339(created_at) {
+        Ok(date) => date
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        Err(_) => created_at.to_string(),
+    }
+}
+
+// replies follow the comment they answer, indented a step deeper. a reply whose parent
+// is not in the list is shown at the top level
+fn push_comments(
+    text: &mut Vec<Text<'static>>,
+    comments: &[&Comment],
+    parent: Option<&str>,
+    depth: usize,
+    width: usize,
+) {
+    let indent = "  ".repeat(depth);
+    for comment in comments.iter() {
+        let is_child = match (parent, comment.parent_id.as_ref()) {
+            (Some(parent), Some(id)) => parent == id,
+            (None, Some(id)) => !comments.iter().any(|other| other.id == *id),
+            (None, None) => true,
+            (Some(_), None) => false,
+        };
+        if !is_child {
+            continue;
+        }
+        text.push(Text::styled(
+            format!(
+                "\n{}{} {}\n",
+                indent,
+                comment.user.name,
+                comment_date(&comment.created_at)
+            ),
+            Style::default().modifier(Modifier::BOLD),
+        ));
+        let body = html2md::parse_html_custom(
+            &comment.body,
+            &HashMap::default(),
+            html2md::Config {
+                max_length: width.saturating_sub(indent.len()),
+                new_line_break: "\n".to_string(),
+                logger: None,
+            },
+        );
+        for line in body.trim().lines() {
+            text.push(Text::raw(format!("{}{}\n", indent, line)));
+        }
+        push_comments(text, comments, Some(&comment.id), depth + 1, width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_keys() {
+        assert_eq!(App::get_key_from("pgup").unwrap(), Key::PageUp);
+        assert_eq!(App::get_key_from("PageDown").unwrap(), Key::PageDown);
+        assert_eq!(App::get_key_from("home").unwrap(), Key::Home);
+        assert_eq!(App::get_key_from("enter").unwrap(), Key::Char('\n'));
+        assert_eq!(App::get_key_from("none").unwrap(), Key::Null);
+        assert_eq!(App::get_key_from("G").unwrap(), Key::Char('G'));
+    }
+
+    #[test]
+    fn function_keys() {
+        assert_eq!(App::get_key_from("f1").unwrap(), Key::F(1));
+        assert_eq!(App::get_key_from("F12").unwrap(), Key::F(12));
+        assert!(App::get_key_from("f13").is_err());
+        assert!(App::get_key_from("f0").is_err());
+    }
+
+    #[test]
+    fn modified_keys() {
+        assert_eq!(App::get_key_from("alt+j").unwrap(), Key::Alt('j'));
+        assert_eq!(App::get_key_from("alt+J").unwrap(), Key::Alt('J'));
+        assert_eq!(App::get_key_from("ctrl+q").unwrap(), Key::Ctrl('q'));
+        // termion never reports an uppercase ctrl key
+        assert_eq!(App::get_key_from("CTRL+Q").unwrap(), Key::Ctrl('q'));
+        assert!(App::get_key_from("ctrl+").is_err());
+        assert!(App::get_key_from("alt+jk").is_err());
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        assert!(App::get_key_from("hyper").is_err());
+        assert!(App::get_key_from("shift+a").is_err());
+    }
+
+    #[test]
+    fn layout_file_overrides_bindings() {
+        let mut layout = KeyLayout::default();
+        apply_layout(
+            &mut layout,
+            "up = \"alt+k\"\nquit = \"ctrl+Q\"\ntop = \"g t\"\ntick_rate = 100\n",
+        )
+        .unwrap();
+        assert_eq!(layout.up, Key::Alt('k'));
+        assert_eq!(layout.quit, Key::Ctrl('q'));
+        assert_eq!(layout.top, vec![Key::Char('g'), Key::Char('t')]);
+        assert_eq!(layout.tick_rate, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn layout_file_adds_action_chords() {
+        let mut layout = KeyLayout::default();
+        apply_layout(
+            &mut layout,
+            "[chords]\nrefresh = \"d d\"\nrelease_notes = \"g n\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            layout.action_chords,
+            vec![
+                ("refresh", vec![Key::Char('d'), Key::Char('d')]),
+                ("release_notes", vec![Key::Char('g'), Key::Char('n')]),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_layout_files_are_errors() {
+        let rejected = |file: &str| apply_layout(&mut KeyLayout::default(), file).is_err();
+        assert!(rejected("search = \"c\"\n"));
+        assert!(rejected("quit = \"ctrl+x\"\n"));
+        assert!(rejected("up = \"hyper\"\n"));
+        assert!(rejected("jump = \"j\"\n"));
+        assert!(rejected("tick_rate = 0\n"));
+        assert!(rejected("top = \"\"\n"));
+        assert!(rejected("[chords]\njump = \"d d\"\n"));
+        assert!(rejected("[chords]\nrefresh = \"r r\"\n"));
+        assert!(rejected("[chords]\nrefresh = \"g\"\n"));
+        assert!(!rejected("search = \"none\"\ncreate = \"none\"\n"));
+    }
+}
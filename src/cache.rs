@@ -0,0 +1,246 @@
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lookup {
+    // straight to Aha, nothing is read or written
+    Network,
+    // a cached copy of any age, Aha when there is none
+    Cached,
+    // Aha, and the cached copy is replaced
+    Refresh,
+}
+
+// responses not saved again for this many ttls, and at least a day, are removed
+const KEEP_TTLS: u64 = 10;
+const KEEP_AT_LEAST: u64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    saved_at: u64,
+    response: Value,
+}
+
+// responses saved on disk by url, one file each
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: Option<PathBuf>,
+    ttl: Duration,
+}
+
+// fnv-1a, file names stay the same from one build to the next unlike DefaultHasher
+fn url_hash(url: &str) -> u64 {
+    url.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn find_updated_at(value: &Value, record: &str) -> Option<String> {
+    match value {
+        Value::Object(fields) => {
+            let found = fields
+                .get("updated_at")
+                .and_then(Value::as_str)
+                .filter(|_| {
+                    fields.get("id").and_then(Value::as_str) == Some(record)
+                        || fields.get("reference_num").and_then(Value::as_str) == Some(record)
+                });
+            match found {
+                Some(updated_at) => Some(updated_at.to_string()),
+                None => fields
+                    .values()
+                    .filter_map(|field| find_updated_at(field, record))
+                    .max(),
+            }
+        }
+        Value::Array(values) => values
+            .iter()
+            .filter_map(|value| find_updated_at(value, record))
+            .max(),
+        _ => None,
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0)
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Cache {
+        Cache {
+            dir: Some(dir),
+            ttl,
+        }
+    }
+
+    pub fn disabled() -> Cache {
+        Cache {
+            dir: None,
+            ttl: Duration::from_secs(0),
+        }
+    }
+
+    fn path(&self, url: &Url) -> Option<PathBuf> {
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{:016x}.json", url_hash(url.as_str()))))
+    }
+
+    // the saved response and whether it is older than the ttl
+    pub fn read(&self, url: &Url) -> Option<(Value, bool)> {
+        let file = fs::read_to_string(self.path(url)?).ok()?;
+        let entry: Entry = serde_json::from_str(&file).ok()?;
+        if entry.url != url.as_str() {
+            return None;
+        }
+        let stale = now().saturating_sub(entry.saved_at) > self.ttl.as_secs();
+        Some((entry.response, stale))
+    }
+
+    // a cache that can not be written is skipped, the response is still good
+    pub fn write(&self, url: &Url, response: &Value) {
+        let path = match self.path(url) {
+            Some(path) => path,
+            None => return,
+        };
+        let entry = Entry {
+            url: url.to_string(),
+            saved_at: now(),
+            response: response.clone(),
+        };
+        if let (Some(dir), Ok(file)) = (path.parent(), serde_json::to_string(&entry)) {
+            let _ = fs::create_dir_all(dir).and_then(|_| fs::write(&path, file));
+        }
+    }
+
+    // the newest updated_at of a record with this id or reference_num in any saved response
+    pub fn updated_at(&self, record: &str) -> Option<String> {
+        let dir = fs::read_dir(self.dir.as_ref()?).ok()?;
+        dir.filter_map(|file| file.ok())
+            .filter_map(|file| fs::read_to_string(file.path()).ok())
+            .filter_map(|file| serde_json::from_str::<Entry>(&file).ok())
+            .filter_map(|entry| find_updated_at(&entry.response, record))
+            .max()
+    }
+
+    // drops responses nothing has saved in a long time so the directory does not keep
+    // every release ever opened. best effort like write
+    pub fn prune(&self) {
+        let dir = match self.dir.as_ref().and_then(|dir| fs::read_dir(dir).ok()) {
+            Some(dir) => dir,
+            None => return,
+        };
+        let keep = Duration::from_secs((self.ttl.as_secs() * KEEP_TTLS).max(KEEP_AT_LEAST));
+        for file in dir.filter_map(|file| file.ok()) {
+            let path = file.path();
+            if path
+                .extension()
+                .map_or(true, |extension| extension != "json")
+            {
+                continue;
+            }
+            let old = file
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .map_or(false, |age| age > keep);
+            if old {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache(name: &str, ttl: u64) -> (Cache, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("aha_cli_cache_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        (Cache::new(dir.clone(), Duration::from_secs(ttl)), dir)
+    }
+
+    fn url(path: &str) -> Url {
+        Url::parse(&format!("https://example.aha.io/api/v1/{}", path)).unwrap()
+    }
+
+    #[test]
+    fn url_hash_is_stable() {
+        assert_eq!(url_hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(url_hash("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn write_then_read() {
+        let (cache, dir) = temp_cache("read", 600);
+        let products = url("products");
+        assert!(cache.read(&products).is_none());
+        cache.write(&products, &json!({"products": [{"id": "1"}]}));
+        let (response, stale) = cache.read(&products).unwrap();
+        assert_eq!(response["products"][0]["id"], "1");
+        assert!(!stale);
+        assert!(cache.read(&url("releases")).is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn old_entry_is_stale() {
+        let (cache, dir) = temp_cache("stale", 600);
+        let products = url("products");
+        let entry = Entry {
+            url: products.to_string(),
+            saved_at: now() - 601,
+            response: json!({}),
+        };
+        fs::create_dir_all(&dir).unwrap();
+        let path = cache.path(&products).unwrap();
+        fs::write(path, serde_json::to_string(&entry).unwrap()).unwrap();
+        assert!(cache.read(&products).unwrap().1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn entry_for_another_url_is_ignored() {
+        let (cache, dir) = temp_cache("other", 600);
+        let products = url("products");
+        let entry = Entry {
+            url: url("releases").to_string(),
+            saved_at: now(),
+            response: json!({}),
+        };
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            cache.path(&products).unwrap(),
+            serde_json::to_string(&entry).unwrap(),
+        )
+        .unwrap();
+        assert!(cache.read(&products).is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn disabled_cache_saves_nothing() {
+        let cache = Cache::disabled();
+        cache.write(&url("products"), &json!({}));
+        assert!(cache.read(&url("products")).is_none());
+    }
+
+    #[test]
+    fn prune_keeps_recent_entries() {
+        let (cache, dir) = temp_cache("prune", 0);
+        cache.write(&url("products"), &json!({}));
+        cache.prune();
+        assert!(cache.read(&url("products")).is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,234 @@
+use super::aha::{
+    Aha, AhaError, CustomFieldGithub, CustomNotes, FeatureCreate, FeatureUpdate, Todo,
+};
+use super::github;
+use super::output::{self, Format, Row};
+use super::release_notes;
+use super::Command;
+use regex::Regex;
+
+// requirements are numbered under their feature, APP-12-1
+pub fn is_requirement(reference: &str) -> bool {
+    Regex::new(r"^[A-Za-z0-9]+-\d+-\d+$")
+        .unwrap()
+        .is_match(reference)
+}
+
+// the row comes from what the write returned, queued and dry run writes only echo
+// what was sent so the reference is filled in from the argument
+pub fn set_pull_request(aha: &Aha, reference: String, url: String) -> Result<Row, failure::Error> {
+    let update = FeatureUpdate {
+        custom_fields: Some(CustomFieldGithub { github_url: url }),
+        ..FeatureUpdate::new()
+    };
+    let mut row = if is_requirement(&reference) {
+        Row::from(&aha.update_requirement(reference.clone(), &update)?)
+    } else {
+        Row::from(&aha.update_feature(reference.clone(), &update)?)
+    };
+    if row.reference_num.is_empty() {
+        row.reference_num = reference;
+    }
+    Ok(row)
+}
+
+// the record was printed already, to-dos that do not load are only a warning
+fn print_todos(todos: Result<Vec<Todo>, AhaError>) {
+    let todos = match todos {
+        Ok(todos) => todos,
+        Err(e) => {
+            eprintln!("warning: could not load to-dos: {}", e);
+            return;
+        }
+    };
+    if todos.is_empty() {
+        return;
+    }
+    println!();
+    for todo in todos.iter() {
+        println!("[{}] {}", if todo.is_done() { "x" } else { " " }, todo.name);
+    }
+}
+
+fn show(aha: &Aha, reference: String) -> Result<(), failure::Error> {
+    if is_requirement(&reference) {
+        let requirement = aha.requirement(reference)?;
+        println!("{} {}", requirement.reference_num, requirement.name);
+        print_details(
+            requirement.status_name(),
+            requirement.assigned_to_user.clone().map(|user| user.name),
+            requirement.url.clone(),
+            requirement.pull_request(),
+        );
+        println!();
+        println!("{}", html2md::parse_html(&requirement.description.body));
+        print_todos(aha.todos("requirements", &requirement.id));
+    } else {
+        let feature = aha.feature(reference)?;
+        println!("{} {}", feature.reference_num, feature.name);
+        print_details(
+            feature.status_name(),
+            feature.assigned_to_user.clone().map(|user| user.name),
+            feature.url.clone(),
+            feature.pull_request(),
+        );
+        for requirement in feature.requirements.iter() {
+            println!(
+                "  {} {} - {}",
+                requirement.reference_num,
+                requirement.name,
+                requirement.status_name()
+            );
+        }
+        println!();
+        println!("{}", html2md::parse_html(&feature.description.body));
+        print_todos(aha.todos("features", &feature.id));
+    }
+    Ok(())
+}
+
+fn print_details(
+    status: &str,
+    assignee: Option<String>,
+    url: Option<String>,
+    pull_request: Option<String>,
+) {
+    println!("Status: {}", status);
+    println!(
+        "Assigned: {}",
+        assignee.unwrap_or_else(|| "Unassigned".to_string())
+    );
+    println!("Url: {}", url.unwrap_or_default());
+    if let Some(pull_request) = pull_request {
+        println!("PR: {}", pull_request);
+    }
+}
+
+fn print_rows(format: Format, rows: &[Row]) {
+    let rendered = output::render(format, rows);
+    if !rendered.is_empty() {
+        println!("{}", rendered);
+    }
+}
+
+// lists default to tab separated lines without a header
+fn print_list(format: Option<Format>, rows: &[Row]) {
+    match format {
+        Some(format) => print_rows(format, rows),
+        None => {
+            for line in output::tsv_lines(rows) {
+                println!("{}", line);
+            }
+        }
+    }
+}
+
+fn reference_rows(aha: &Aha, reference: &str) -> Result<Vec<Row>, failure::Error> {
+    Ok(if is_requirement(reference) {
+        vec![Row::from(&aha.requirement(reference.to_string())?)]
+    } else {
+        output::feature_rows(&[aha.feature(reference.to_string())?])
+    })
+}
+
+pub fn run(command: &Command, format: Option<Format>, aha: &Aha) -> Result<(), failure::Error> {
+    match command {
+        Command::Products => {
+            let rows: Vec<Row> = aha.projects()?.iter().map(Row::from).collect();
+            print_list(format, &rows);
+        }
+        Command::Releases { product } => {
+            let rows: Vec<Row> = aha
+                .releases(product.clone())?
+                .iter()
+                .map(Row::from)
+                .collect();
+            print_list(format, &rows);
+        }
+        Command::Features { release } => {
+            let rows = output::feature_rows(&aha.features(release.clone())?);
+            print_list(format, &rows);
+        }
+        Command::Show { reference } => match format {
+            Some(format) => print_rows(format, &reference_rows(aha, reference)?),
+            None => show(aha, reference.clone())?,
+        },
+        Command::CreateFeature {
+            release,
+            name,
+            description,
+            needs_notes,
+        } => {
+            let release = aha.release(release.clone())?;
+            let mut new_feature = FeatureCreate::new();
+            new_feature.name = name.clone();
+            new_feature.description = description.clone();
+            new_feature.release_id = release.id;
+            if *needs_notes {
+                new_feature.custom_fields = Some(CustomNotes {
+                    notes: "Required".to_string(),
+                });
+            }
+            let feature = aha.send_feature(&new_feature)?;
+            match format {
+                Some(format) => print_rows(format, &[Row::from(&feature)]),
+                None => println!(
+                    "feature created: {} {}",
+                    feature.reference_num,
+                    feature.url.unwrap_or_default()
+                ),
+            }
+        }
+        Command::ReleaseNotes {
+            release,
+            group_by,
+            html,
+            output,
+        } => {
+            // notes are a document, not rows
+            if format.is_some() {
+                bail!("--format does not apply to release-notes, it writes markdown or --html");
+            }
+            let release = aha.release(release.clone())?;
+            let features = aha.features(release.id.clone())?;
+            let notes = if *html {
+                release_notes::html(&release, &features, *group_by)
+            } else {
+                release_notes::markdown(&release, &features, *group_by)
+            };
+            match output {
+                Some(path) => {
+                    std::fs::write(path, notes)?;
+                    println!("release notes written to {}", path);
+                }
+                None => print!("{}", notes),
+            }
+        }
+        Command::SetPr { reference, url } => {
+            let url = match url {
+                Some(url) => url.clone(),
+                None => github::infer_pull_request_url()?,
+            };
+            let row = set_pull_request(aha, reference.clone(), url.clone())?;
+            match format {
+                Some(format) => print_rows(format, &[row]),
+                None => println!("{} pull request set to {}", reference, url),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requirements_have_a_second_number() {
+        assert!(is_requirement("APP-12-1"));
+        assert!(is_requirement("A1-3-22"));
+        assert!(!is_requirement("APP-12"));
+        assert!(!is_requirement("APP-R-1"));
+        assert!(!is_requirement("APP-12-1 "));
+    }
+}
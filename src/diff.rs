@@ -0,0 +1,85 @@
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+// line diff from the longest common subsequence, descriptions are small enough for the table
+pub fn diff_lines(old: &str, new: &str) -> Vec<Change> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push(Change::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            changes.push(Change::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    changes.extend(
+        old[i..]
+            .iter()
+            .map(|line| Change::Removed(line.to_string())),
+    );
+    changes.extend(new[j..].iter().map(|line| Change::Added(line.to_string())));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn same(line: &str) -> Change {
+        Change::Same(line.to_string())
+    }
+
+    fn removed(line: &str) -> Change {
+        Change::Removed(line.to_string())
+    }
+
+    fn added(line: &str) -> Change {
+        Change::Added(line.to_string())
+    }
+
+    #[test]
+    fn unchanged_text_is_all_same() {
+        assert_eq!(diff_lines("a\nb", "a\nb"), vec![same("a"), same("b")]);
+    }
+
+    #[test]
+    fn changed_line_is_removed_then_added() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nB\nc"),
+            vec![same("a"), removed("b"), added("B"), same("c")]
+        );
+    }
+
+    #[test]
+    fn lines_added_and_removed_at_the_ends() {
+        assert_eq!(
+            diff_lines("a\nb", "b\nc"),
+            vec![removed("a"), same("b"), added("c")]
+        );
+        assert_eq!(diff_lines("", "new"), vec![added("new")]);
+        assert_eq!(diff_lines("old", ""), vec![removed("old")]);
+    }
+}
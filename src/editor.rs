@@ -0,0 +1,351 @@
+use termion::event::Key;
+use tui::{
+    style::{Modifier, Style},
+    widgets::Text,
+};
+
+pub enum EditorEvent {
+    Changed,
+    Submit,
+    Ignored,
+}
+
+// a small text area for the create popups, positions are in chars not bytes
+pub struct Editor {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+    multiline: bool,
+}
+
+impl Editor {
+    pub fn single_line() -> Editor {
+        Editor {
+            lines: vec![String::new()],
+            row: 0,
+            col: 0,
+            multiline: false,
+        }
+    }
+
+    pub fn multiline() -> Editor {
+        Editor {
+            multiline: true,
+            ..Editor::single_line()
+        }
+    }
+
+    pub fn is_multiline(&self) -> bool {
+        self.multiline
+    }
+
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    fn byte_index(&self, row: usize, col: usize) -> usize {
+        self.lines[row]
+            .char_indices()
+            .nth(col)
+            .map(|(i, _)| i)
+            .unwrap_or_else(|| self.lines[row].len())
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = vec![String::new()];
+        self.row = 0;
+        self.col = 0;
+        self.insert_str(text);
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let index = self.byte_index(self.row, self.col);
+        self.lines[self.row].insert(index, c);
+        self.col += 1;
+    }
+
+    pub fn insert_newline(&mut self) {
+        if !self.multiline {
+            return;
+        }
+        let index = self.byte_index(self.row, self.col);
+        let rest = self.lines[self.row].split_off(index);
+        self.row += 1;
+        self.col = 0;
+        self.lines.insert(self.row, rest);
+    }
+
+    // pasted newlines only survive in a multiline editor
+    pub fn insert_str(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\r' => {}
+                '\n' if self.multiline => self.insert_newline(),
+                '\n' => self.insert_char(' '),
+                c => self.insert_char(c),
+            }
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let index = self.byte_index(self.row, self.col);
+            self.lines[self.row].remove(index);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.col < self.line_len(self.row) {
+            let index = self.byte_index(self.row, self.col);
+            self.lines[self.row].remove(index);
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    pub fn left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+        }
+    }
+
+    pub fn right(&mut self) {
+        if self.col < self.line_len(self.row) {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    pub fn up(&mut self) {
+        if self.row > 0 {
+            self.row -= 1;
+            self.col = self.col.min(self.line_len(self.row));
+        }
+    }
+
+    pub fn down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = self.col.min(self.line_len(self.row));
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.col = self.line_len(self.row);
+    }
+
+    // start of the previous word, wrapping to the line above
+    pub fn word_left(&mut self) {
+        if self.col == 0 {
+            self.left();
+            return;
+        }
+        let chars: Vec<char> = self.lines[self.row].chars().collect();
+        let mut col = self.col;
+        while col > 0 && chars[col - 1].is_whitespace() {
+            col -= 1;
+        }
+        while col > 0 && !chars[col - 1].is_whitespace() {
+            col -= 1;
+        }
+        self.col = col;
+    }
+
+    // end of the next word, wrapping to the line below
+    pub fn word_right(&mut self) {
+        let chars: Vec<char> = self.lines[self.row].chars().collect();
+        if self.col == chars.len() {
+            self.right();
+            return;
+        }
+        let mut col = self.col;
+        while col < chars.len() && chars[col].is_whitespace() {
+            col += 1;
+        }
+        while col < chars.len() && !chars[col].is_whitespace() {
+            col += 1;
+        }
+        self.col = col;
+    }
+
+    // enter is a newline in a multiline editor, ctrl+s always submits
+    pub fn handle_key(&mut self, key: Key) -> EditorEvent {
+        match key {
+            Key::Ctrl('s') => return EditorEvent::Submit,
+            Key::Char('\n') if !self.multiline => return EditorEvent::Submit,
+            Key::Char('\n') | Key::Alt('\n') | Key::Alt('\r') => self.insert_newline(),
+            Key::Char(c) => self.insert_char(c),
+            Key::Backspace => self.backspace(),
+            Key::Delete | Key::Ctrl('d') => self.delete(),
+            Key::Left | Key::Ctrl('b') => self.left(),
+            Key::Right | Key::Ctrl('f') => self.right(),
+            Key::Up | Key::Ctrl('p') => self.up(),
+            Key::Down | Key::Ctrl('n') => self.down(),
+            Key::Home | Key::Ctrl('a') => self.home(),
+            Key::End | Key::Ctrl('e') => self.end(),
+            Key::Alt('b') => self.word_left(),
+            Key::Alt('f') => self.word_right(),
+            _ => return EditorEvent::Ignored,
+        }
+        EditorEvent::Changed
+    }
+
+    // the char under the cursor is drawn reversed
+    pub fn render(&self) -> Vec<Text<'static>> {
+        let cursor_style = Style::default().modifier(Modifier::REVERSED);
+        let mut text = vec![];
+        for (row, line) in self.lines.iter().enumerate() {
+            if row == self.row {
+                let chars: Vec<char> = line.chars().collect();
+                let before: String = chars[..self.col].iter().collect();
+                let (cursor, after) = if self.col < chars.len() {
+                    (
+                        chars[self.col].to_string(),
+                        chars[self.col + 1..].iter().collect(),
+                    )
+                } else {
+                    (" ".to_string(), String::new())
+                };
+                text.push(Text::raw(before));
+                text.push(Text::styled(cursor, cursor_style));
+                text.push(Text::raw(format!("{}\n", after)));
+            } else {
+                text.push(Text::raw(format!("{}\n", line)));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(editor: &mut Editor, keys: &[Key]) {
+        for key in keys {
+            editor.handle_key(*key);
+        }
+    }
+
+    #[test]
+    fn enter_submits_a_single_line() {
+        let mut editor = Editor::single_line();
+        typed(&mut editor, &[Key::Char('h'), Key::Char('i')]);
+        match editor.handle_key(Key::Char('\n')) {
+            EditorEvent::Submit => {}
+            _ => panic!("enter should submit"),
+        }
+        assert_eq!(editor.text(), "hi");
+    }
+
+    #[test]
+    fn enter_is_a_newline_in_a_multiline_editor() {
+        let mut editor = Editor::multiline();
+        typed(
+            &mut editor,
+            &[Key::Char('a'), Key::Char('\n'), Key::Char('b')],
+        );
+        assert_eq!(editor.text(), "a\nb");
+        assert_eq!(editor.row(), 1);
+        match editor.handle_key(Key::Ctrl('s')) {
+            EditorEvent::Submit => {}
+            _ => panic!("ctrl+s should submit"),
+        }
+    }
+
+    #[test]
+    fn pasted_newlines_depend_on_the_editor() {
+        let mut single = Editor::single_line();
+        single.insert_str("one\r\ntwo");
+        assert_eq!(single.text(), "one two");
+        let mut multi = Editor::multiline();
+        multi.insert_str("one\r\ntwo");
+        assert_eq!(multi.text(), "one\ntwo");
+    }
+
+    #[test]
+    fn edits_count_chars_not_bytes() {
+        let mut editor = Editor::single_line();
+        editor.insert_str("héllo");
+        typed(&mut editor, &[Key::Home, Key::Right, Key::Right]);
+        editor.handle_key(Key::Backspace);
+        assert_eq!(editor.text(), "hllo");
+        editor.insert_char('é');
+        assert_eq!(editor.text(), "héllo");
+    }
+
+    #[test]
+    fn backspace_and_delete_join_lines() {
+        let mut editor = Editor::multiline();
+        editor.insert_str("ab\ncd");
+        typed(&mut editor, &[Key::Home, Key::Backspace]);
+        assert_eq!(editor.text(), "abcd");
+        assert_eq!(editor.col, 2);
+        let mut editor = Editor::multiline();
+        editor.insert_str("ab\ncd");
+        typed(&mut editor, &[Key::Up, Key::End, Key::Delete]);
+        assert_eq!(editor.text(), "abcd");
+    }
+
+    #[test]
+    fn moving_wraps_between_lines_and_keeps_the_column_in_range() {
+        let mut editor = Editor::multiline();
+        editor.insert_str("long line\nab");
+        editor.handle_key(Key::Up);
+        assert_eq!((editor.row(), editor.col), (0, 2));
+        editor.handle_key(Key::End);
+        editor.handle_key(Key::Down);
+        assert_eq!((editor.row(), editor.col), (1, 2));
+        editor.handle_key(Key::Right);
+        assert_eq!((editor.row(), editor.col), (1, 2));
+        editor.handle_key(Key::Home);
+        editor.handle_key(Key::Left);
+        assert_eq!((editor.row(), editor.col), (0, 9));
+    }
+
+    #[test]
+    fn word_moves() {
+        let mut editor = Editor::single_line();
+        editor.insert_str("one two  three");
+        editor.handle_key(Key::Alt('b'));
+        assert_eq!(editor.col, 9);
+        editor.handle_key(Key::Alt('b'));
+        assert_eq!(editor.col, 4);
+        editor.handle_key(Key::Alt('f'));
+        assert_eq!(editor.col, 7);
+    }
+
+    #[test]
+    fn unknown_keys_are_ignored() {
+        let mut editor = Editor::single_line();
+        match editor.handle_key(Key::F(5)) {
+            EditorEvent::Ignored => {}
+            _ => panic!("f5 should be ignored"),
+        }
+        assert_eq!(editor.text(), "");
+    }
+}
@@ -0,0 +1,76 @@
+use super::util::event::{Events, DISABLE_BRACKETED_PASTE, ENABLE_BRACKETED_PASTE};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process::Command;
+use termion::raw::RawTerminal;
+use termion::screen::{ToAlternateScreen, ToMainScreen};
+
+// $VISUAL then $EDITOR, vi when neither is set. "code --wait" style values keep their args
+fn editor_command() -> Vec<String> {
+    let command = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_default();
+    let parts: Vec<String> = command.split_whitespace().map(String::from).collect();
+    if parts.is_empty() {
+        vec!["vi".to_string()]
+    } else {
+        parts
+    }
+}
+
+pub fn edit(text: &str) -> Result<String, failure::Error> {
+    let path = env::temp_dir().join(format!("aha-cli-{}.md", std::process::id()));
+    fs::write(&path, text)?;
+    let command = editor_command();
+    let result = match Command::new(&command[0])
+        .args(&command[1..])
+        .arg(&path)
+        .status()
+    {
+        Ok(status) if status.success() => fs::read_to_string(&path)
+            .map(|saved| saved.trim_end().to_string())
+            .map_err(failure::Error::from),
+        Ok(status) => Err(format_err!("{} exited with {}", command.join(" "), status)),
+        Err(e) => Err(format_err!("could not start {}: {}", command[0], e)),
+    };
+    let _ = fs::remove_file(&path);
+    result
+}
+
+fn leave_tui<W: Write>(raw: &RawTerminal<W>) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(
+        stdout,
+        "{}{}{}",
+        DISABLE_BRACKETED_PASTE,
+        ToMainScreen,
+        termion::cursor::Show
+    )?;
+    stdout.flush()?;
+    raw.suspend_raw_mode()
+}
+
+fn enter_tui<W: Write>(raw: &RawTerminal<W>) -> io::Result<()> {
+    raw.activate_raw_mode()?;
+    let mut stdout = io::stdout();
+    write!(stdout, "{}{}", ToAlternateScreen, ENABLE_BRACKETED_PASTE)?;
+    stdout.flush()
+}
+
+// hands the terminal to the editor and takes it back, the caller redraws after
+pub fn edit_in_terminal<W: Write>(
+    raw: &RawTerminal<W>,
+    events: &Events,
+    text: &str,
+) -> Result<String, failure::Error> {
+    events.pause();
+    let result = leave_tui(raw)
+        .map_err(failure::Error::from)
+        .and_then(|_| edit(text));
+    let restored = enter_tui(raw);
+    events.resume();
+    let text = result?;
+    restored?;
+    Ok(text)
+}
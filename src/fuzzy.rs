@@ -0,0 +1,154 @@
+use tui::widgets::ListState;
+
+// a single item that matched the filter text
+pub struct FuzzyMatch {
+    pub index: usize,
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+// scores text against the pattern. every pattern char has to be found in order.
+// consecutive chars and chars at the start of a word score higher, gaps cost.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let text: Vec<char> = text.chars().collect();
+    let mut positions = vec![];
+    let mut score = 0;
+    let mut start = 0;
+    for p in pattern.chars().filter(|c| !c.is_whitespace()) {
+        let p = p.to_lowercase().next().unwrap_or(p);
+        let found =
+            (start..text.len()).find(|&i| text[i].to_lowercase().next().unwrap_or(text[i]) == p)?;
+        score += 1;
+        if let Some(&last) = positions.last() {
+            if found == last + 1 {
+                score += 5;
+            } else {
+                score -= (found - last) as i64 / 2;
+            }
+        } else {
+            score -= found as i64 / 4;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        positions.push(found);
+        start = found + 1;
+    }
+    Some((score, positions))
+}
+
+pub struct FilteredList {
+    pub query: String,
+    pub matches: Vec<FuzzyMatch>,
+    pub state: ListState,
+}
+
+impl FilteredList {
+    pub fn new() -> FilteredList {
+        FilteredList {
+            query: "".to_string(),
+            matches: vec![],
+            state: ListState::default(),
+        }
+    }
+
+    // rank the names against the query, best match first
+    pub fn update(&mut self, names: &[String]) {
+        let mut matches: Vec<FuzzyMatch> = names
+            .iter()
+            .enumerate()
+            .filter_map(|(index, name)| {
+                fuzzy_match(&self.query, name).map(|(score, positions)| FuzzyMatch {
+                    index,
+                    score,
+                    positions,
+                })
+            })
+            .collect();
+        matches.sort_by(|a, b| b.score.cmp(&a.score).then(a.index.cmp(&b.index)));
+        self.matches = matches;
+        if self.matches.is_empty() {
+            self.state.select(None);
+        } else {
+            self.state.select(Some(0));
+        }
+    }
+
+    // index into the unfiltered list
+    pub fn selected_index(&self) -> Option<usize> {
+        self.state
+            .selected()
+            .and_then(|i| self.matches.get(i))
+            .map(|m| m.index)
+    }
+
+    pub fn next(&mut self) {
+        if let Some(i) = self.state.selected() {
+            if i + 1 < self.matches.len() {
+                self.state.select(Some(i + 1));
+            }
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            if i > 0 {
+                self.state.select(Some(i - 1));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chars_must_appear_in_order() {
+        assert_eq!(fuzzy_match("abc", "a-b-c").unwrap().1, vec![0, 2, 4]);
+        assert!(fuzzy_match("cba", "abc").is_none());
+        assert!(fuzzy_match("x", "abc").is_none());
+    }
+
+    #[test]
+    fn case_and_spaces_are_ignored() {
+        assert_eq!(fuzzy_match("Ex P", "export").unwrap().1, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn consecutive_and_word_start_matches_score_higher() {
+        let (together, _) = fuzzy_match("exp", "export feature").unwrap();
+        let (apart, _) = fuzzy_match("exp", "e x a m p l e").unwrap();
+        assert!(together > apart);
+        let (word, _) = fuzzy_match("f", "big feature").unwrap();
+        let (inside, _) = fuzzy_match("f", "bigfeature").unwrap();
+        assert!(word > inside);
+    }
+
+    #[test]
+    fn empty_pattern_matches_everything() {
+        assert_eq!(fuzzy_match("", "anything"), Some((0, vec![])));
+    }
+
+    #[test]
+    fn filtered_list_ranks_best_first() {
+        let names = vec![
+            "settings page".to_string(),
+            "search".to_string(),
+            "other".to_string(),
+        ];
+        let mut list = FilteredList::new();
+        list.query = "sea".to_string();
+        list.update(&names);
+        assert_eq!(list.matches.len(), 2);
+        assert_eq!(list.selected_index(), Some(1));
+        list.next();
+        assert_eq!(list.selected_index(), Some(0));
+        list.next();
+        assert_eq!(list.selected_index(), Some(0));
+
+        list.query = "zzz".to_string();
+        list.update(&names);
+        assert_eq!(list.selected_index(), None);
+    }
+}
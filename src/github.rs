@@ -0,0 +1,105 @@
+use regex::Regex;
+use serde_json::Value;
+use std::process::Command;
+use url::Url;
+
+fn git(args: &[&str]) -> Result<String, failure::Error> {
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// owner and repo name from a github remote in either the ssh or https form
+pub fn github_repo(remote: &str) -> Option<(String, String)> {
+    let re = Regex::new(r"github\.com[:/]([^/]+)/([^/]+?)(\.git)?/?$").unwrap();
+    re.captures(remote)
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+}
+
+// branch names can hold # & + or %, so the query is encoded
+fn pulls_url(owner: &str, repo: &str, branch: &str) -> Url {
+    let mut url = Url::parse("https://api.github.com/repos/").unwrap();
+    url.path_segments_mut()
+        .unwrap()
+        .pop_if_empty()
+        .extend(&[owner, repo, "pulls"]);
+    url.query_pairs_mut()
+        .append_pair("state", "all")
+        .append_pair("head", &format!("{}:{}", owner, branch));
+    url
+}
+
+// looks up the pull request for the current branch of the repo in the working directory
+pub fn infer_pull_request_url() -> Result<String, failure::Error> {
+    let remote = git(&["remote", "get-url", "origin"])?;
+    let (owner, repo) = match github_repo(&remote) {
+        Some(found) => found,
+        None => bail!("origin {} is not a github remote", remote),
+    };
+    let branch = git(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+
+    let uri = pulls_url(&owner, &repo, &branch).to_string();
+    let mut request = reqwest::Client::new().get(&uri).header(
+        reqwest::header::USER_AGENT,
+        "Rust aha api v1 (Becker@aha.io)",
+    );
+    // private repos need a token
+    if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+        request = request.header(reqwest::header::AUTHORIZATION, format!("token {}", token));
+    }
+    let mut response = request.send()?;
+    if !response.status().is_success() {
+        bail!(
+            "github returned {} for {}/{}",
+            response.status(),
+            owner,
+            repo
+        );
+    }
+    let pulls: Value = response.json()?;
+    match pulls[0]["html_url"].as_str() {
+        Some(url) => Ok(url.to_string()),
+        None => bail!("no pull request found for {} in {}/{}", branch, owner, repo),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn github_repo_reads_ssh_and_https_remotes() {
+        let expected = Some(("org".to_string(), "repo".to_string()));
+        assert_eq!(github_repo("git@github.com:org/repo.git"), expected);
+        assert_eq!(github_repo("https://github.com/org/repo.git"), expected);
+        assert_eq!(github_repo("https://github.com/org/repo/"), expected);
+        assert_eq!(github_repo("https://github.com/org/repo"), expected);
+    }
+
+    #[test]
+    fn pulls_url_encodes_the_branch() {
+        let url = pulls_url("org", "repo", "fix/a#1&b+c%d");
+        assert_eq!(url.path(), "/repos/org/repo/pulls");
+        let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        assert_eq!(
+            query,
+            vec![
+                ("state".to_string(), "all".to_string()),
+                ("head".to_string(), "org:fix/a#1&b+c%d".to_string()),
+            ]
+        );
+        assert_eq!(url.fragment(), None);
+    }
+
+    #[test]
+    fn github_repo_ignores_other_hosts() {
+        assert_eq!(github_repo("git@gitlab.com:org/repo.git"), None);
+        assert_eq!(github_repo("/srv/git/repo.git"), None);
+    }
+}
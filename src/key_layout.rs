@@ -1,4 +1,9 @@
+use std::time::{Duration, Instant};
 use termion::event::Key;
+
+// a half typed chord or count is dropped after this long
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1500);
+
 pub struct KeyLayout {
     pub up: Key,
     pub down: Key,
@@ -13,6 +18,132 @@ pub struct KeyLayout {
     pub quit: Key,
     pub search: Key,
     pub create: Key,
+    pub filter: Key,
+    pub status: Key,
+    pub assign: Key,
+    pub pull_request: Key,
+    pub infer_pull_request: Key,
+    pub release_notes: Key,
+    pub external_editor: Key,
+    pub edit: Key,
+    pub refresh: Key,
+    pub comment: Key,
+    pub todos: Key,
+    pub next_feature: Key,
+    pub previous_feature: Key,
+    pub page_down: Key,
+    pub page_up: Key,
+    pub half_page_down: Key,
+    pub half_page_up: Key,
+    pub top: Vec<Key>,
+    pub bottom: Vec<Key>,
+    // extra key sequences for actions, "d d" for refresh
+    pub action_chords: Vec<(&'static str, Vec<Key>)>,
+    pub tick_rate: Duration,
+}
+
+impl KeyLayout {
+    // every binding with the action it triggers, aliases share an action
+    pub fn bindings(&self) -> Vec<(&'static str, &'static str, Key)> {
+        vec![
+            ("up", "up", self.up),
+            ("up_arrow", "up", self.up_arrow),
+            ("down", "down", self.down),
+            ("down_arrow", "down", self.down_arrow),
+            ("left", "left", self.left),
+            ("left_arrow", "left", self.left_arrow),
+            ("right", "right", self.right),
+            ("right_arrow", "right", self.right_arrow),
+            ("right_alt", "right", self.right_alt),
+            ("escape", "escape", self.escape),
+            ("quit", "quit", self.quit),
+            ("search", "search", self.search),
+            ("create", "create", self.create),
+            ("filter", "filter", self.filter),
+            ("status", "status", self.status),
+            ("assign", "assign", self.assign),
+            ("pull_request", "pull_request", self.pull_request),
+            (
+                "infer_pull_request",
+                "infer_pull_request",
+                self.infer_pull_request,
+            ),
+            ("release_notes", "release_notes", self.release_notes),
+            ("external_editor", "external_editor", self.external_editor),
+            ("edit", "edit", self.edit),
+            ("refresh", "refresh", self.refresh),
+            ("comment", "comment", self.comment),
+            ("todos", "todos", self.todos),
+            ("next_feature", "next_feature", self.next_feature),
+            (
+                "previous_feature",
+                "previous_feature",
+                self.previous_feature,
+            ),
+            ("page_down", "page_down", self.page_down),
+            ("page_up", "page_up", self.page_up),
+            ("half_page_down", "half_page_down", self.half_page_down),
+            ("half_page_up", "half_page_up", self.half_page_up),
+        ]
+    }
+
+    pub fn chords(&self) -> Vec<(&'static str, &Vec<Key>)> {
+        let mut chords = vec![("top", &self.top), ("bottom", &self.bottom)];
+        chords.extend(self.action_chords.iter().map(|(name, keys)| (*name, keys)));
+        chords
+    }
+
+    // the key a finished action chord stands for
+    pub fn key_for(&self, name: &str) -> Option<Key> {
+        self.bindings()
+            .into_iter()
+            .find(|(binding, _, _)| *binding == name)
+            .map(|(_, _, key)| key)
+    }
+
+    pub fn is_bound(&self, key: Key) -> bool {
+        self.bindings().iter().any(|(_, _, bound)| *bound == key)
+    }
+
+    pub fn check_conflicts(&self) -> Result<(), failure::Error> {
+        let bindings = self.bindings();
+        let mut conflicts = vec![];
+        let chords = self.chords();
+        for (i, (name, chord)) in chords.iter().enumerate() {
+            // a chord starting with a bound key would never finish
+            if let Some((bound, _, key)) = bindings
+                .iter()
+                .find(|(_, _, key)| *key != Key::Null && chord.first() == Some(key))
+            {
+                conflicts.push(format!("{} starts with {} {:?}", name, bound, key));
+            }
+            if self.key_for(name) == Some(Key::Null) {
+                conflicts.push(format!("{} has a chord but is bound to none", name));
+            }
+            for (other_name, other_chord) in chords.iter().skip(i + 1) {
+                if !chord.is_empty()
+                    && (chord.starts_with(other_chord) || other_chord.starts_with(chord))
+                {
+                    conflicts.push(format!("{} and {} overlap", name, other_name));
+                }
+            }
+        }
+        for (i, (name, action, key)) in bindings.iter().enumerate() {
+            // none unbinds an action
+            if *key == Key::Null {
+                continue;
+            }
+            for (other_name, other_action, other_key) in bindings.iter().skip(i + 1) {
+                if key == other_key && action != other_action {
+                    conflicts.push(format!("{} and {} are both {:?}", name, other_name, key));
+                }
+            }
+        }
+        if !conflicts.is_empty() {
+            bail!("conflicting key bindings: {}", conflicts.join(", "));
+        }
+        Ok(())
+    }
 }
 
 impl Default for KeyLayout {
@@ -23,7 +154,7 @@ impl Default for KeyLayout {
             down: Key::Char('j'),
             down_arrow: Key::Down,
             left: Key::Char('h'),
-            left_arrow: Key::Right,
+            left_arrow: Key::Left,
             right: Key::Char('l'),
             right_alt: Key::Char('\n'),
             right_arrow: Key::Right,
@@ -31,6 +162,255 @@ impl Default for KeyLayout {
             quit: Key::Char('q'),
             search: Key::Char('s'),
             create: Key::Char('c'),
+            filter: Key::Char('/'),
+            status: Key::Char('w'),
+            assign: Key::Char('a'),
+            pull_request: Key::Char('P'),
+            // works inside the pull request popup
+            infer_pull_request: Key::Ctrl('g'),
+            release_notes: Key::Char('N'),
+            // not a printable key so it also works while typing in a popup
+            external_editor: Key::Ctrl('x'),
+            edit: Key::Char('e'),
+            refresh: Key::Char('r'),
+            comment: Key::Char('C'),
+            todos: Key::Char('t'),
+            next_feature: Key::Char('n'),
+            previous_feature: Key::Char('p'),
+            page_down: Key::PageDown,
+            page_up: Key::PageUp,
+            half_page_down: Key::Ctrl('d'),
+            half_page_up: Key::Ctrl('u'),
+            top: vec![Key::Char('g'), Key::Char('g')],
+            bottom: vec![Key::Char('G')],
+            action_chords: vec![],
+            tick_rate: Duration::from_millis(250),
         }
     }
 }
+
+pub enum Sequence {
+    // waiting for the rest of a chord or count
+    Pending,
+    Key(Key, usize),
+    Chord(&'static str, Option<usize>),
+}
+
+// vim style counts like 5j and chords like gg
+#[derive(Default)]
+pub struct KeySequence {
+    count: Option<usize>,
+    keys: Vec<Key>,
+    started: Option<Instant>,
+}
+
+impl KeySequence {
+    pub fn push(&mut self, key: Key, layout: &KeyLayout) -> Sequence {
+        self.expire();
+        self.started = Some(Instant::now());
+        if let Key::Char(c) = key {
+            if let Some(digit) = c.to_digit(10) {
+                // a leading 0 or a digit bound to an action is a plain key
+                if self.keys.is_empty()
+                    && (digit != 0 || self.count.is_some())
+                    && !layout.is_bound(key)
+                {
+                    let count = self.count.unwrap_or(0);
+                    self.count = Some(count.saturating_mul(10).saturating_add(digit as usize));
+                    return Sequence::Pending;
+                }
+            }
+        }
+
+        self.keys.push(key);
+        let chords = layout.chords();
+        if let Some((name, _)) = chords.iter().find(|(_, chord)| **chord == self.keys) {
+            let count = self.count;
+            self.reset();
+            return Sequence::Chord(name, count);
+        }
+        if chords
+            .iter()
+            .any(|(_, chord)| chord.len() > self.keys.len() && chord.starts_with(&self.keys))
+        {
+            return Sequence::Pending;
+        }
+        // a broken chord falls through as the key that broke it
+        let count = self.count.unwrap_or(1);
+        self.reset();
+        Sequence::Key(key, count)
+    }
+
+    pub fn expire(&mut self) {
+        if let Some(started) = self.started {
+            if started.elapsed() > SEQUENCE_TIMEOUT {
+                self.reset();
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.count = None;
+        self.keys.clear();
+        self.started = None;
+    }
+
+    pub fn pending(&self) -> Option<String> {
+        if self.count.is_none() && self.keys.is_empty() {
+            return None;
+        }
+        let mut text = self
+            .count
+            .map(|count| count.to_string())
+            .unwrap_or_default();
+        for key in self.keys.iter() {
+            match key {
+                Key::Char(c) => text.push(*c),
+                other => text.push_str(&format!("{:?}", other)),
+            }
+        }
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_all(keys: &str, layout: &KeyLayout) -> Vec<Sequence> {
+        let mut sequence = KeySequence::default();
+        keys.chars()
+            .map(|c| sequence.push(Key::Char(c), layout))
+            .collect()
+    }
+
+    fn is_pending(sequence: &Sequence) -> bool {
+        matches!(sequence, Sequence::Pending)
+    }
+
+    #[test]
+    fn plain_key_has_a_count_of_one() {
+        match push_all("j", &KeyLayout::default()).pop().unwrap() {
+            Sequence::Key(key, count) => assert_eq!((key, count), (Key::Char('j'), 1)),
+            _ => panic!("expected a key"),
+        }
+    }
+
+    #[test]
+    fn count_prefix() {
+        let mut sequences = push_all("12j", &KeyLayout::default());
+        match sequences.pop().unwrap() {
+            Sequence::Key(key, count) => assert_eq!((key, count), (Key::Char('j'), 12)),
+            _ => panic!("expected a key"),
+        }
+        assert!(sequences.iter().all(is_pending));
+    }
+
+    #[test]
+    fn chords_with_and_without_a_count() {
+        match push_all("gg", &KeyLayout::default()).pop().unwrap() {
+            Sequence::Chord(name, count) => assert_eq!((name, count), ("top", None)),
+            _ => panic!("expected a chord"),
+        }
+        match push_all("5G", &KeyLayout::default()).pop().unwrap() {
+            Sequence::Chord(name, count) => assert_eq!((name, count), ("bottom", Some(5))),
+            _ => panic!("expected a chord"),
+        }
+    }
+
+    #[test]
+    fn broken_chord_is_the_key_that_broke_it() {
+        match push_all("gj", &KeyLayout::default()).pop().unwrap() {
+            Sequence::Key(key, count) => assert_eq!((key, count), (Key::Char('j'), 1)),
+            _ => panic!("expected a key"),
+        }
+    }
+
+    #[test]
+    fn leading_zero_and_bound_digits_are_keys() {
+        match push_all("0", &KeyLayout::default()).pop().unwrap() {
+            Sequence::Key(key, _) => assert_eq!(key, Key::Char('0')),
+            _ => panic!("expected a key"),
+        }
+        let layout = KeyLayout {
+            search: Key::Char('1'),
+            ..KeyLayout::default()
+        };
+        match push_all("1", &layout).pop().unwrap() {
+            Sequence::Key(key, _) => assert_eq!(key, Key::Char('1')),
+            _ => panic!("expected a key"),
+        }
+    }
+
+    #[test]
+    fn pending_shows_the_half_typed_sequence() {
+        let layout = KeyLayout::default();
+        let mut sequence = KeySequence::default();
+        assert_eq!(sequence.pending(), None);
+        sequence.push(Key::Char('3'), &layout);
+        sequence.push(Key::Char('g'), &layout);
+        assert_eq!(sequence.pending(), Some("3g".to_string()));
+        sequence.reset();
+        assert_eq!(sequence.pending(), None);
+    }
+
+    #[test]
+    fn action_chords_finish_like_top_and_bottom() {
+        let layout = KeyLayout {
+            action_chords: vec![("refresh", vec![Key::Char('d'), Key::Char('d')])],
+            ..KeyLayout::default()
+        };
+        let mut sequences = push_all("3dd", &layout);
+        match sequences.pop().unwrap() {
+            Sequence::Chord(name, count) => assert_eq!((name, count), ("refresh", Some(3))),
+            _ => panic!("expected a chord"),
+        }
+        assert!(sequences.iter().all(is_pending));
+        assert_eq!(layout.key_for("refresh"), Some(Key::Char('r')));
+    }
+
+    #[test]
+    fn default_layout_has_no_conflicts() {
+        assert!(KeyLayout::default().check_conflicts().is_ok());
+    }
+
+    #[test]
+    fn conflicts_are_reported() {
+        let layout = KeyLayout {
+            search: Key::Char('c'),
+            ..KeyLayout::default()
+        };
+        assert!(layout.check_conflicts().is_err());
+
+        let layout = KeyLayout {
+            top: vec![Key::Char('j'), Key::Char('j')],
+            ..KeyLayout::default()
+        };
+        assert!(layout.check_conflicts().is_err());
+
+        let layout = KeyLayout {
+            bottom: vec![Key::Char('g')],
+            ..KeyLayout::default()
+        };
+        assert!(layout.check_conflicts().is_err());
+
+        // a chord for an unbound action would do nothing
+        let layout = KeyLayout {
+            refresh: Key::Null,
+            action_chords: vec![("refresh", vec![Key::Char('d'), Key::Char('d')])],
+            ..KeyLayout::default()
+        };
+        assert!(layout.check_conflicts().is_err());
+    }
+
+    #[test]
+    fn aliases_and_unbound_keys_are_not_conflicts() {
+        let layout = KeyLayout {
+            right_alt: Key::Char('l'),
+            search: Key::Null,
+            create: Key::Null,
+            ..KeyLayout::default()
+        };
+        assert!(layout.check_conflicts().is_ok());
+    }
+}
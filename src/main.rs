@@ -1,10 +1,21 @@
 mod app;
+mod cli;
+mod diff;
+mod editor;
+mod external_editor;
+mod fuzzy;
 mod key_layout;
+mod outbox;
+mod output;
+mod release_notes;
 #[allow(dead_code)]
 mod util;
+mod worker;
 
 extern crate chrono;
 extern crate html2md;
+extern crate libc;
+extern crate pulldown_cmark;
 
 extern crate dirs;
 extern crate dotenv;
@@ -15,6 +26,7 @@ extern crate termion;
 extern crate failure;
 extern crate reqwest;
 extern crate serde;
+#[macro_use]
 extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;
@@ -32,11 +44,13 @@ use std::fs::File;
 use std::io::prelude::*;
 use structopt::StructOpt;
 mod aha;
+mod cache;
+mod github;
 
 use aha::Aha;
 use app::{App, Popup};
 use std::{error::Error, io};
-use termion::{raw::IntoRawMode, screen::AlternateScreen};
+use termion::{event::Key, raw::IntoRawMode, screen::AlternateScreen};
 use tui::{
     backend::TermionBackend,
     layout::{Constraint, Corner, Direction, Layout},
@@ -44,7 +58,7 @@ use tui::{
     widgets::{Block, Borders, List, Paragraph, Text},
     Terminal,
 };
-use util::{event::Events, StatefulList};
+use util::{event, event::Events, StatefulList};
 
 #[derive(StructOpt, Debug)]
 pub struct Opt {
@@ -58,6 +72,82 @@ pub struct Opt {
     verbose: bool,
     #[structopt(short = "c", long = "config")]
     config_file: Option<String>,
+    /// Output of commands: json, jsonl, csv, tsv or table
+    #[structopt(short = "f", long = "format")]
+    format: Option<output::Format>,
+    /// Always load products, releases and features from Aha! instead of the local cache
+    #[structopt(long = "no-cache")]
+    no_cache: bool,
+    /// Seconds before a cached list is refreshed
+    #[structopt(long = "cache-ttl", default_value = "600")]
+    cache_ttl: u64,
+    /// Browse the saved lists and queue changes until the next run without it
+    #[structopt(long = "offline")]
+    offline: bool,
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum Command {
+    /// List products
+    #[structopt(name = "products")]
+    Products,
+    /// List the unshipped releases of a product
+    #[structopt(name = "releases")]
+    Releases {
+        /// Product id or reference prefix
+        product: String,
+    },
+    /// List the features of a release
+    #[structopt(name = "features")]
+    Features {
+        /// Release id or reference like APP-R-1
+        release: String,
+    },
+    /// Show a feature or requirement
+    #[structopt(name = "show")]
+    Show {
+        /// Feature or requirement reference like APP-12 or APP-12-1
+        reference: String,
+    },
+    /// Create a feature in a release
+    #[structopt(name = "create-feature")]
+    CreateFeature {
+        /// Release id or reference like APP-R-1
+        #[structopt(long = "release")]
+        release: String,
+        #[structopt(long = "name")]
+        name: String,
+        #[structopt(long = "description", default_value = "")]
+        description: String,
+        /// Mark the feature as needing release notes
+        #[structopt(long = "needs-notes")]
+        needs_notes: bool,
+    },
+    /// Build release notes from the features that need notes
+    #[structopt(name = "release-notes")]
+    ReleaseNotes {
+        /// Release id or reference like APP-R-1
+        release: String,
+        /// Group features by status or tag
+        #[structopt(long = "group-by", default_value = "status")]
+        group_by: release_notes::GroupBy,
+        /// Write html instead of markdown
+        #[structopt(long = "html")]
+        html: bool,
+        /// File to write, defaults to stdout
+        #[structopt(short = "o", long = "output")]
+        output: Option<String>,
+    },
+    /// Set the pull request of a feature or requirement
+    #[structopt(name = "set-pr")]
+    SetPr {
+        /// Feature or requirement reference like APP-12 or APP-12-1
+        reference: String,
+        /// Defaults to the pull request of the current git branch
+        url: Option<String>,
+    },
 }
 #[derive(Debug, Deserialize)]
 struct Config {
@@ -114,11 +204,26 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
+// lines a wrapped paragraph takes, close enough for scrolling
+fn wrapped_lines(text: &[Text], width: u16) -> u16 {
+    let mut joined = String::new();
+    for part in text.iter() {
+        match part {
+            Text::Raw(data) | Text::Styled(data, _) => joined.push_str(data),
+        }
+    }
+    let width = width.max(1) as usize;
+    joined
+        .split('\n')
+        .map(|line| ((line.chars().count() + width - 1) / width).max(1) as u16)
+        .fold(0, |total: u16, lines| total.saturating_add(lines))
+}
+
 fn load_config() -> Result<(Env, Opt), Box<dyn Error>> {
     //copied config
     let opt = Opt::from_args();
     if opt.verbose {
-        println!("{:?}", opt);
+        eprintln!("{:?}", opt);
     }
     let home_dir = dirs::home_dir().expect("Could not find home path");
 
@@ -128,13 +233,13 @@ fn load_config() -> Result<(Env, Opt), Box<dyn Error>> {
     };
 
     if opt.verbose {
-        println!("{:?}", path_name);
+        eprintln!("{:?}", path_name);
     }
     let config_path = fs::canonicalize(&path_name);
     let config_info: Option<Config> = match config_path {
         Ok(path) => {
             if opt.verbose {
-                println!("found {:?}", path_name);
+                eprintln!("found {:?}", path_name);
             }
             let display = path.display();
             let mut file = match File::open(&path) {
@@ -152,7 +257,7 @@ fn load_config() -> Result<(Env, Opt), Box<dyn Error>> {
         }
         Err(e) => {
             if !opt.silent {
-                println!("did not find {:?}, {}", path_name, e);
+                eprintln!("did not find {:?}, {}", path_name, e);
             }
             None
         }
@@ -176,7 +281,7 @@ fn load_config() -> Result<(Env, Opt), Box<dyn Error>> {
     }
 
     if opt.verbose {
-        println!("config updated");
+        eprintln!("config updated");
     }
 
     Ok((config, opt))
@@ -190,29 +295,68 @@ fn main() -> Result<(), Box<dyn Error>> {
         config.workflow_email,
         &opt,
     );
+    // changes queued while offline go out before anything else
+    let mut replayed = None;
+    if !opt.offline && !opt.dry_run {
+        match outbox::replay(&aha) {
+            Ok(summary) => replayed = summary,
+            Err(e) => replayed = Some(e.to_string()),
+        }
+        if let Some(summary) = replayed.as_ref() {
+            eprintln!("{}", summary);
+        }
+        // offline browses whatever was saved, however old
+        aha.cache.prune();
+    }
+    if let Some(command) = opt.cmd.as_ref() {
+        let result = cli::run(command, opt.format, &aha);
+        // stderr so --format output can still be piped
+        for entry in aha.dry_run_log.lock().unwrap().iter() {
+            eprintln!("dry run: {}", entry);
+        }
+        if opt.offline {
+            if let Ok(entries) = aha.outbox.entries() {
+                if !entries.is_empty() {
+                    eprintln!("{} changes queued for the next online run", entries.len());
+                }
+            }
+        }
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    // Terminal initialization
-    let stdout = io::stdout().into_raw_mode()?;
-    let stdout = AlternateScreen::from(stdout);
-    let backend = TermionBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    //terminal.hide_cursor()?;
-
-    let mut events = Events::new();
+    // load before raw mode so a failure prints normally, a cached list is refreshed
+    // once the ui is up
+    let mut products = aha
+        .pages::<aha::Product>(aha.products_url(), "products".to_string())
+        .cached(cache::Lookup::Cached);
+    let aha_projects = match products.all() {
+        Ok(projects) => projects,
+        Err(e) => {
+            eprintln!("Can not load projects: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     // App
     let mut app = App::new();
-    let aha_projects = aha.projects();
+    if let Some(summary) = replayed {
+        app.debug_txt = summary;
+    }
     app.items = StatefulList::with_items(
         aha_projects
             .iter()
-            .map(|project| (project["name"].to_string(), project.clone()))
+            .map(|project| (project.name.clone(), project.clone()))
             .collect(),
     );
 
     let home_dir = dirs::home_dir().expect("Could not find home path");
 
-    let path_name = format!("{}/.aha_cli_cache", home_dir.display());
+    // a bad layout is reported before the terminal is taken over
+    let path_name = format!("{}/.aha_cli_layout.toml", home_dir.display());
     match File::open(&path_name) {
         Err(why) => {
             if opt.verbose {
@@ -221,15 +365,37 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
         Ok(mut file) => {
             let mut s = String::new();
-            match file.read_to_string(&mut s) {
-                Err(why) => panic!("couldn't read {}: {}", path_name, why),
-                Ok(_) => (),
+            if let Err(why) = file.read_to_string(&mut s) {
+                eprintln!("couldn't read {}: {}", path_name, why);
+                std::process::exit(1);
+            }
+            if let Err(e) = app.load_layout(s) {
+                eprintln!("invalid key layout in {}: {}", path_name, e);
+                std::process::exit(1);
             }
-            app.load_history(s, &aha);
         }
     };
 
-    let path_name = format!("{}/.aha_cli_layout.toml", home_dir.display());
+    // Terminal initialization
+    // raw mode is held on its own so it can be suspended for $EDITOR
+    let raw = io::stdout().into_raw_mode()?;
+    let mut stdout = io::stdout();
+    write!(stdout, "{}", event::ENABLE_BRACKETED_PASTE)?;
+    stdout.flush()?;
+    let stdout = AlternateScreen::from(stdout);
+    let backend = TermionBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+    //terminal.hide_cursor()?;
+
+    let events = Events::with_config(event::Config {
+        tick_rate: app.layout.tick_rate,
+    });
+    app.worker = worker::Worker::new(events.sender());
+    if products.is_stale() {
+        app.refresh_stale_products(&aha);
+    }
+
+    let path_name = format!("{}/.aha_cli_cache", home_dir.display());
     match File::open(&path_name) {
         Err(why) => {
             if opt.verbose {
@@ -242,7 +408,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                 Err(why) => panic!("couldn't read {}: {}", path_name, why),
                 Ok(_) => (),
             }
-            app.load_layout(s);
+            app.load_history(s, &aha);
         }
     };
     loop {
@@ -279,15 +445,25 @@ fn main() -> Result<(), Box<dyn Error>> {
             let style = Style::default().fg(Color::Black).bg(Color::White);
 
             let items = app.items.items.iter().map(|i| Text::raw(i.0.clone()));
+            let projects_title = app.pane_title("Projects", worker::Pane::Products);
             let items = List::new(items)
-                .block(Block::default().borders(Borders::ALL).title("Projects"))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(&projects_title),
+                )
                 .highlight_style(style.fg(Color::Black).modifier(Modifier::BOLD))
                 .highlight_symbol(">");
             f.render_stateful_widget(items, release_chunks[0], &mut app.items.state);
 
+            let releases_title = app.pane_title("Releases", worker::Pane::Releases);
             let releases_items = app.releases.items.iter().map(|i| Text::raw(i.0.clone()));
             let releases_items = List::new(releases_items)
-                .block(Block::default().borders(Borders::ALL).title("Releases"))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(&releases_title),
+                )
                 .highlight_style(style.fg(Color::Black).modifier(Modifier::BOLD))
                 .highlight_symbol(">");
             f.render_stateful_widget(releases_items, release_chunks[1], &mut app.releases.state);
@@ -297,88 +473,274 @@ fn main() -> Result<(), Box<dyn Error>> {
                 feature_list = 40;
                 feature_show = 55;
             }
+            let dry_run_size = if opt.dry_run { 20 } else { 0 };
             let feature_chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints(
                     [
                         Constraint::Percentage(feature_list),
-                        Constraint::Percentage(feature_show),
+                        Constraint::Percentage(feature_show - dry_run_size),
+                        Constraint::Percentage(dry_run_size),
                         Constraint::Percentage(5),
                     ]
                     .as_ref(),
                 )
                 .split(chunks[1]);
 
+            let features_title = app.pane_title("Features", worker::Pane::Features);
             let feature_items = app.features.items.iter().map(|i| Text::raw(i.0.clone()));
             let feature_items = List::new(feature_items)
-                .block(Block::default().borders(Borders::ALL).title("Features"))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(&features_title),
+                )
                 .highlight_style(style.fg(Color::Black).modifier(Modifier::BOLD))
                 .highlight_symbol(">");
             f.render_stateful_widget(feature_items, feature_chunks[0], &mut app.features.state);
-            let title = app.feature_title.clone();
-            let feature_vec = app.format_selected_feature(feature_chunks[1].width as usize);
-            let paragraph = Paragraph::new(feature_vec.iter())
-                .block(Block::default().title(&title).borders(Borders::ALL))
-                .wrap(true);
-            f.render_widget(paragraph, feature_chunks[1]);
+            let detail_area = feature_chunks[1];
+            let detail_width = detail_area.width.saturating_sub(2);
+            let detail_height = detail_area.height.saturating_sub(2);
+            let detail_scroll = app.detail_scroll;
+            let detail_lines = {
+                let feature_vec = app.format_selected_feature(detail_area.width as usize);
+                let detail_lines = wrapped_lines(&feature_vec, detail_width);
+                let paragraph = Paragraph::new(feature_vec.iter())
+                    .block(Block::default().borders(Borders::ALL))
+                    .wrap(true)
+                    .scroll(detail_scroll);
+                f.render_widget(paragraph, detail_area);
+                detail_lines
+            };
+            app.set_detail_size(detail_lines, detail_height);
+            // the block is drawn again on top so the title can show the scroll position
+            let title = if detail_lines > detail_height {
+                format!(
+                    "{} [{}-{}/{}]",
+                    app.feature_title,
+                    detail_scroll + 1,
+                    (detail_scroll + detail_height).min(detail_lines),
+                    detail_lines
+                )
+            } else {
+                app.feature_title.clone()
+            };
+            f.render_widget(
+                Block::default().title(&title).borders(Borders::ALL),
+                detail_area,
+            );
+
+            if opt.dry_run {
+                // newest first so the latest request is never clipped
+                let dry_run_vec: Vec<Text> = aha
+                    .dry_run_log
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .rev()
+                    .map(|entry| Text::raw(format!("{}\n", entry)))
+                    .collect();
+                let dry_run_paragraph = Paragraph::new(dry_run_vec.iter())
+                    .block(Block::default().title("dry run").borders(Borders::ALL))
+                    .wrap(true);
+                f.render_widget(dry_run_paragraph, feature_chunks[2]);
+            }
 
             let events_list = app
                 .events
                 .iter()
                 .map(|&(_, _)| Text::raw(app.debug_txt.to_string()));
+            // a half typed count or chord shows up next to the title
+            let mut dbg_title = match app.sequence.pending() {
+                Some(pending) => format!("dbg {}", pending),
+                None => "dbg".to_string(),
+            };
+            if opt.offline {
+                dbg_title.push_str(" offline");
+            }
+            // saves and picker loads spin here
+            let dbg_title = app
+                .worker
+                .busy_title(&dbg_title, &[worker::Pane::Save, worker::Pane::Picker]);
             let events_list = List::new(events_list)
-                .block(Block::default().borders(Borders::ALL).title("dbg"))
+                .block(Block::default().borders(Borders::ALL).title(&dbg_title))
                 .start_corner(Corner::BottomLeft);
-            f.render_widget(events_list, feature_chunks[2]);
+            f.render_widget(events_list, feature_chunks[3]);
+            if app.popup == Popup::Filter {
+                let (list_title, area) = match app.active_layer {
+                    app::Screen::Project => ("Projects", release_chunks[0]),
+                    app::Screen::Release => ("Releases", release_chunks[1]),
+                    _ => ("Features", feature_chunks[0]),
+                };
+                let filter_title = format!("{} /{}", list_title, app.filter.query);
+                let filter_lines = app.filter_lines(
+                    style.fg(Color::Black).modifier(Modifier::BOLD),
+                    Style::default().fg(Color::Yellow).modifier(Modifier::BOLD),
+                );
+                let offset = app
+                    .filter
+                    .state
+                    .selected()
+                    .unwrap_or(0)
+                    .saturating_sub(area.height.saturating_sub(3) as usize);
+                let filter_paragraph = Paragraph::new(filter_lines.iter())
+                    .block(Block::default().borders(Borders::ALL).title(&filter_title))
+                    .scroll(offset as u16);
+                f.render_widget(Clear, area);
+                f.render_widget(filter_paragraph, area);
+            }
             match app.popup {
-                Popup::Text => {
-                    let block = Block::default()
-                        .title(&app.text_box_title)
-                        .borders(Borders::ALL);
-                    let text = Text::raw(app.text_box.clone());
-                    let text_vec = vec![text];
-                    let create_paragraph = Paragraph::new(text_vec.iter()).block(block).wrap(true);
+                Popup::Text | Popup::Edit | Popup::Comment | Popup::Todo => {
+                    let (title, height) = if app.editor.is_multiline() {
+                        (
+                            format!(
+                                "{} - enter for a new line, ctrl+s to save",
+                                app.text_box_title
+                            ),
+                            60,
+                        )
+                    } else {
+                        (app.text_box_title.clone(), 20)
+                    };
+                    let block = Block::default().title(&title).borders(Borders::ALL);
                     let size = f.size();
-                    let area = centered_rect(60, 20, size);
+                    let area = centered_rect(60, height, size);
+                    // keep the cursor line in view
+                    let visible = area.height.saturating_sub(2) as usize;
+                    let scroll = (app.editor.row() + 1).saturating_sub(visible) as u16;
+                    let text_vec = app.editor.render();
+                    let create_paragraph = Paragraph::new(text_vec.iter())
+                        .block(block)
+                        .wrap(true)
+                        .scroll(scroll);
                     f.render_widget(Clear, area); //this clears out the background
                     f.render_widget(create_paragraph, area);
                 }
+                Popup::PullRequest => {
+                    let title = format!(
+                        "Pull request URL, {:?} looks up the current git branch",
+                        app.layout.infer_pull_request
+                    );
+                    let block = Block::default().title(&title).borders(Borders::ALL);
+                    let text = Text::raw(app.text_box.clone());
+                    let text_vec = vec![text];
+                    let pr_paragraph = Paragraph::new(text_vec.iter()).block(block).wrap(true);
+                    let area = centered_rect(60, 20, f.size());
+                    f.render_widget(Clear, area); //this clears out the background
+                    f.render_widget(pr_paragraph, area);
+                }
+                Popup::Pick => {
+                    let pick_title = app.pick_title();
+                    let pick_items = app.picker.items.iter().map(|i| Text::raw(i.0.clone()));
+                    let pick_items = List::new(pick_items)
+                        .block(Block::default().borders(Borders::ALL).title(&pick_title))
+                        .highlight_style(style.fg(Color::Black).modifier(Modifier::BOLD))
+                        .highlight_symbol(">");
+                    let area = centered_rect(40, 50, f.size());
+                    f.render_widget(Clear, area); //this clears out the background
+                    f.render_stateful_widget(pick_items, area, &mut app.picker.state);
+                }
                 Popup::Search => {
-                    let block = Block::default().title("Search").borders(Borders::ALL);
+                    let title = app.search_title();
+                    let block = Block::default().title(&title).borders(Borders::ALL);
                     let text = Text::raw(app.text_box.clone());
                     let text_vec = vec![text];
                     let create_paragraph = Paragraph::new(text_vec.iter()).block(block).wrap(true);
                     let size = f.size();
                     let area = centered_rect(60, 50, size);
+                    let search_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+                        .split(area);
                     f.render_widget(Clear, area); //this clears out the background
-                    f.render_widget(create_paragraph, area);
+                    f.render_widget(create_paragraph, search_chunks[0]);
+
+                    let result_items = app
+                        .search_results
+                        .items
+                        .iter()
+                        .map(|i| Text::raw(i.0.clone()));
+                    let result_items = List::new(result_items)
+                        .block(Block::default().borders(Borders::ALL).title("Results"))
+                        .highlight_style(style.fg(Color::Black).modifier(Modifier::BOLD))
+                        .highlight_symbol(">");
+                    f.render_stateful_widget(
+                        result_items,
+                        search_chunks[1],
+                        &mut app.search_results.state,
+                    );
+                }
+                Popup::Diff => {
+                    let diff_title = app.diff_title();
+                    let diff_text = app.diff_text();
+                    let diff_paragraph = Paragraph::new(diff_text.iter())
+                        .block(Block::default().title(&diff_title).borders(Borders::ALL))
+                        .wrap(true)
+                        .scroll(app.diff_scroll);
+                    let area = centered_rect(70, 70, f.size());
+                    f.render_widget(Clear, area); //this clears out the background
+                    f.render_widget(diff_paragraph, area);
                 }
                 _ => {}
             }
         })?;
 
         if let Ok(event) = events.next() {
-            let result = if app.popup == Popup::Text && app.releases.state.selected().is_some() {
-                let x = if app.active_layer == app::Screen::Feature {
+            // finished requests update the lists whatever popup is open
+            let result = if let event::Event::Api(reply) = event {
+                app.handle_api(reply, &aha);
+                Some(())
+            } else if let event::Event::Input(Key::Null) = event {
+                // none unbinds an action as Key::Null, a real NUL from ctrl+space must not
+                // trigger every unbound action
+                Some(())
+            } else if app.popup == Popup::Text && app.releases.state.selected().is_some() {
+                if app.active_layer == app::Screen::Feature {
                     app.handle_create_requirement_popup(event, &aha)
                 } else {
                     app.handle_create_popup(event, &aha)
-                };
-                events.disable_exit_key();
-                x
+                }
+            } else if app.popup == Popup::Edit {
+                app.handle_edit_popup(event)
+            } else if app.popup == Popup::Comment {
+                app.handle_comment_popup(event, &aha)
+            } else if app.popup == Popup::Todo {
+                app.handle_todo_popup(event, &aha)
+            } else if app.popup == Popup::Diff {
+                app.handle_diff_popup(event, &aha)
+            } else if app.popup == Popup::PullRequest {
+                app.handle_pull_request_popup(event, &aha)
+            } else if app.popup == Popup::Pick {
+                app.handle_pick_popup(event, &aha)
+            } else if app.popup == Popup::Filter {
+                app.handle_filter_popup(event, &aha)
             } else if app.popup == Popup::Search {
-                let x = app.handle_search_popup(event, &aha);
-                events.disable_exit_key();
-                x
+                app.handle_search_popup(event, &aha)
             } else {
-                events.enable_exit_key();
                 app.handle_nav(event, &aha)
             };
             if result.is_none() {
                 break;
             }
+            app.load_comments(&aha);
+            app.load_todos(&aha);
+            if let Some(edit) = app.external_edit.take() {
+                let result = external_editor::edit_in_terminal(&raw, &events, &edit.text);
+                terminal.clear()?;
+                app.finish_external_edit(edit, result, &aha);
+            }
+        }
+    }
+
+    // writes still on their way are not cut off by quitting
+    while app.worker.is_loading(worker::Pane::Save) {
+        if let Ok(event::Event::Api(reply)) = events.next() {
+            app.handle_api(reply, &aha);
         }
     }
 
+    let mut stdout = io::stdout();
+    write!(stdout, "{}", event::DISABLE_BRACKETED_PASTE)?;
+    stdout.flush()?;
     Ok(())
 }
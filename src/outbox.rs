@@ -0,0 +1,557 @@
+use super::aha::{Aha, AhaError};
+use chrono::DateTime;
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+// the made up id and reference_num handed back for a record created while offline
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub id: String,
+    pub reference_num: String,
+}
+
+impl Placeholder {
+    pub fn new(prefix: &str, number: usize) -> Placeholder {
+        Placeholder {
+            id: format!("{}-{}", prefix, number),
+            reference_num: format!("{}-{}", prefix.to_uppercase(), number),
+        }
+    }
+
+    fn number(&self) -> usize {
+        self.id
+            .rsplit('-')
+            .next()
+            .and_then(|number| number.parse().ok())
+            .unwrap_or(0)
+    }
+}
+
+// a write made with --offline, sent the next time the cli runs online
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    pub method: String,
+    pub uri: String,
+    pub base: String,
+    pub body: Value,
+    pub queued_at: i64,
+    // updated_at of the record in the saved lists the change was made against
+    #[serde(default)]
+    pub seen: Option<String>,
+    #[serde(default)]
+    pub placeholder: Option<Placeholder>,
+}
+
+impl Entry {
+    fn describe(&self) -> String {
+        format!("{} {}", self.method, self.uri)
+    }
+
+    // the id or reference_num the uri ends with
+    fn record(&self) -> &str {
+        self.uri.rsplit('/').next().unwrap_or("")
+    }
+
+    // swaps placeholder segments of the uri for the real ids
+    fn swap_placeholders(&mut self, swaps: &HashMap<String, String>) {
+        self.uri = self
+            .uri
+            .split('/')
+            .map(|segment| swaps.get(segment).map_or(segment, String::as_str))
+            .collect::<Vec<&str>>()
+            .join("/");
+    }
+}
+
+// one json entry per line so a queued write is never rewritten in place
+#[derive(Clone)]
+pub struct Outbox {
+    path: Option<PathBuf>,
+}
+
+impl Outbox {
+    pub fn new(path: Option<PathBuf>) -> Outbox {
+        Outbox { path }
+    }
+
+    fn path(&self) -> Result<&PathBuf, failure::Error> {
+        self.path
+            .as_ref()
+            .ok_or_else(|| format_err!("no home directory for the outbox"))
+    }
+
+    fn conflicts_path(&self) -> Result<PathBuf, failure::Error> {
+        Ok(self.path()?.with_extension("conflicts.jsonl"))
+    }
+
+    pub fn entries(&self) -> Result<Vec<Entry>, failure::Error> {
+        let path = self.path()?;
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let mut entries = vec![];
+        for line in fs::read_to_string(path)?.lines() {
+            if !line.trim().is_empty() {
+                entries.push(serde_json::from_str(line)?);
+            }
+        }
+        Ok(entries)
+    }
+
+    fn append(path: &PathBuf, entry: &Entry) -> Result<(), failure::Error> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    // a create gets a placeholder numbered past every one still queued
+    pub fn push(
+        &self,
+        method: &Method,
+        uri: &str,
+        base: &str,
+        body: &Value,
+        seen: Option<String>,
+    ) -> Result<Entry, failure::Error> {
+        let placeholder = if *method == Method::POST {
+            let last = self
+                .entries()?
+                .iter()
+                .filter_map(|entry| entry.placeholder.as_ref().map(Placeholder::number))
+                .max()
+                .unwrap_or(0);
+            Some(Placeholder::new("offline", last + 1))
+        } else {
+            None
+        };
+        let entry = Entry {
+            method: method.to_string(),
+            uri: uri.to_string(),
+            base: base.to_string(),
+            body: body.clone(),
+            queued_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs() as i64)
+                .unwrap_or(0),
+            seen,
+            placeholder,
+        };
+        Outbox::append(self.path()?, &entry)?;
+        Ok(entry)
+    }
+
+    fn save(&self, entries: &[Entry]) -> Result<(), failure::Error> {
+        let path = self.path()?;
+        if entries.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        let lines: Result<Vec<String>, serde_json::Error> =
+            entries.iter().map(serde_json::to_string).collect();
+        fs::write(path, format!("{}\n", lines?.join("\n")))?;
+        Ok(())
+    }
+
+    fn reject(&self, entry: &Entry) -> Result<(), failure::Error> {
+        Outbox::append(&self.conflicts_path()?, entry)
+    }
+}
+
+// a record changed on Aha since the copy the write was made against would be overwritten.
+// records this replay already wrote have moved on from that copy and are not checked
+fn conflict<G>(entry: &Entry, written: &HashSet<String>, get: G) -> Result<Option<String>, AhaError>
+where
+    G: FnOnce(Url, String) -> Result<Value, AhaError>,
+{
+    if entry.method != "PUT" || written.contains(entry.record()) {
+        return Ok(None);
+    }
+    let url = match Url::parse(&entry.uri) {
+        Ok(url) => url,
+        Err(e) => return Ok(Some(format!("has a bad url: {}", e))),
+    };
+    let current = match get(url, entry.base.clone()) {
+        Ok(current) => current,
+        Err(AhaError::Status { status: 404, .. }) => {
+            return Ok(Some("the record is gone".to_string()))
+        }
+        Err(e) => return Err(e),
+    };
+    let updated_at = match current["updated_at"].as_str() {
+        Some(updated_at) => updated_at,
+        None => return Ok(None),
+    };
+    match entry.seen {
+        Some(ref seen) if seen != updated_at => Ok(Some(format!(
+            "changed on Aha! at {}, the change was made against {}",
+            updated_at, seen
+        ))),
+        Some(_) => Ok(None),
+        // nothing was saved to compare with, when it was queued is all there is
+        None => match DateTime::parse_from_rfc3339(updated_at) {
+            Ok(date) if date.timestamp() > entry.queued_at => Ok(Some(format!(
+                "changed on Aha! at {} after it was queued",
+                date
+            ))),
+            _ => Ok(None),
+        },
+    }
+}
+
+// sends queued writes in order. conflicts and rejected writes are reported and kept in
+// the conflicts file, the rest stay queued if Aha can not be reached
+pub fn replay(aha: &Aha) -> Result<Option<String>, failure::Error> {
+    replay_with(
+        &aha.outbox,
+        |method, uri, body, base| aha.send(method, uri, body, base),
+        |url, base| aha.get(url, base),
+    )
+}
+
+fn replay_with<S, G>(outbox: &Outbox, send: S, get: G) -> Result<Option<String>, failure::Error>
+where
+    S: Fn(Method, String, &Value, &str) -> Result<Value, AhaError>,
+    G: Fn(Url, String) -> Result<Value, AhaError>,
+{
+    let mut entries = outbox.entries()?;
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    let (mut sent, mut conflicts) = (0, 0);
+    // placeholder ids and reference_nums of sent creates, and the real ones
+    let mut swaps: HashMap<String, String> = HashMap::new();
+    // placeholders of creates that were not sent
+    let mut lost: HashSet<String> = HashSet::new();
+    let mut written: HashSet<String> = HashSet::new();
+    while !entries.is_empty() {
+        let entry = entries[0].clone();
+        let method = Method::from_bytes(entry.method.as_bytes())?;
+        let result = match entry.uri.split('/').find(|segment| lost.contains(*segment)) {
+            Some(segment) => Ok(Some(format!("{} was never created", segment))),
+            None => conflict(&entry, &written, &get).and_then(|found| match found {
+                Some(reason) => Ok(Some(reason)),
+                None => send(method, entry.uri.clone(), &entry.body, &entry.base).map(|record| {
+                    written.insert(entry.record().to_string());
+                    if let Some(placeholder) = entry.placeholder.as_ref() {
+                        for (made_up, key) in vec![
+                            (&placeholder.id, "id"),
+                            (&placeholder.reference_num, "reference_num"),
+                        ] {
+                            if let Some(real) = record[key].as_str() {
+                                swaps.insert(made_up.clone(), real.to_string());
+                                written.insert(real.to_string());
+                            }
+                        }
+                    }
+                    None
+                }),
+            }),
+        };
+        let delivered = match result {
+            Ok(None) => {
+                sent += 1;
+                true
+            }
+            Ok(Some(reason)) => {
+                conflicts += 1;
+                eprintln!("outbox conflict: {} {}", entry.describe(), reason);
+                outbox.reject(&entry)?;
+                false
+            }
+            // nothing was decided about this write, it goes again on the next run
+            Err(e @ AhaError::Transport(_))
+            | Err(e @ AhaError::RateLimited(_))
+            | Err(e @ AhaError::Auth(_)) => {
+                outbox.save(&entries)?;
+                bail!(
+                    "outbox: {} sent, {} still queued, {}",
+                    sent,
+                    entries.len(),
+                    e
+                );
+            }
+            Err(e) => {
+                conflicts += 1;
+                eprintln!("outbox rejected: {} {}", entry.describe(), e);
+                outbox.reject(&entry)?;
+                false
+            }
+        };
+        if let (Some(placeholder), false) = (entry.placeholder.as_ref(), delivered) {
+            lost.insert(placeholder.id.clone());
+            lost.insert(placeholder.reference_num.clone());
+        }
+        entries.remove(0);
+        // later writes to a record created here go to its real id, also when they stay queued
+        for later in entries.iter_mut() {
+            later.swap_placeholders(&swaps);
+        }
+        outbox.save(&entries)?;
+    }
+    let mut summary = format!("outbox: {} sent", sent);
+    if conflicts > 0 {
+        summary.push_str(&format!(
+            ", {} not sent and kept in {}",
+            conflicts,
+            outbox.conflicts_path()?.display()
+        ));
+    }
+    Ok(Some(summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    const API: &str = "https://example.aha.io/api/v1";
+
+    fn temp_outbox(name: &str) -> Outbox {
+        let path = std::env::temp_dir().join(format!(
+            "aha_cli_outbox_{}_{}.jsonl",
+            name,
+            std::process::id()
+        ));
+        let outbox = Outbox::new(Some(path));
+        let _ = fs::remove_file(outbox.path().unwrap());
+        let _ = fs::remove_file(outbox.conflicts_path().unwrap());
+        outbox
+    }
+
+    fn clean(outbox: &Outbox) {
+        let _ = fs::remove_file(outbox.path().unwrap());
+        let _ = fs::remove_file(outbox.conflicts_path().unwrap());
+    }
+
+    fn rejected(outbox: &Outbox) -> Vec<Entry> {
+        Outbox::new(Some(outbox.conflicts_path().unwrap()))
+            .entries()
+            .unwrap()
+    }
+
+    fn put(outbox: &Outbox, path: &str, seen: Option<&str>) {
+        let uri = format!("{}/{}", API, path);
+        outbox
+            .push(
+                &Method::PUT,
+                &uri,
+                "feature",
+                &json!({}),
+                seen.map(String::from),
+            )
+            .unwrap();
+    }
+
+    fn post(outbox: &Outbox, path: &str) -> Placeholder {
+        let uri = format!("{}/{}", API, path);
+        let entry = outbox
+            .push(&Method::POST, &uri, "feature", &json!({}), None)
+            .unwrap();
+        entry.placeholder.unwrap()
+    }
+
+    // a fake Aha whose records move to a new updated_at on every write
+    struct Remote {
+        updated_at: RefCell<HashMap<String, String>>,
+        sent: RefCell<Vec<String>>,
+    }
+
+    impl Remote {
+        fn new(records: &[(&str, &str)]) -> Remote {
+            Remote {
+                updated_at: RefCell::new(
+                    records
+                        .iter()
+                        .map(|(id, at)| (id.to_string(), at.to_string()))
+                        .collect(),
+                ),
+                sent: RefCell::new(vec![]),
+            }
+        }
+
+        fn send(&self, method: Method, uri: String) -> Result<Value, AhaError> {
+            self.sent.borrow_mut().push(format!("{} {}", method, uri));
+            let id = uri.rsplit('/').next().unwrap().to_string();
+            self.updated_at
+                .borrow_mut()
+                .insert(id, "2020-06-01T00:00:00Z".to_string());
+            Ok(json!({ "id": "100", "reference_num": "APP-7" }))
+        }
+
+        fn get(&self, url: Url) -> Result<Value, AhaError> {
+            let id = url.path().rsplit('/').next().unwrap().to_string();
+            match self.updated_at.borrow().get(&id) {
+                Some(at) => Ok(json!({ "id": id, "updated_at": at })),
+                None => Err(AhaError::Status {
+                    status: 404,
+                    body: "not found".to_string(),
+                }),
+            }
+        }
+
+        fn replay(&self, outbox: &Outbox) -> Result<Option<String>, failure::Error> {
+            replay_with(
+                outbox,
+                |method, uri, _, _| self.send(method, uri),
+                |url, _| self.get(url),
+            )
+        }
+    }
+
+    #[test]
+    fn push_saves_entries_and_numbers_placeholders() {
+        let outbox = temp_outbox("push");
+        assert_eq!(post(&outbox, "features"), Placeholder::new("offline", 1));
+        put(&outbox, "features/1", Some("2020-01-01T00:00:00Z"));
+        assert_eq!(post(&outbox, "features"), Placeholder::new("offline", 2));
+        let entries = outbox.entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].seen, Some("2020-01-01T00:00:00Z".to_string()));
+        // a placeholder still queued is never handed out twice
+        outbox.save(&entries[1..]).unwrap();
+        assert_eq!(post(&outbox, "features"), Placeholder::new("offline", 3));
+        outbox.save(&[]).unwrap();
+        assert!(!outbox.path().unwrap().exists());
+        clean(&outbox);
+    }
+
+    #[test]
+    fn replay_sends_in_order() {
+        let outbox = temp_outbox("order");
+        put(&outbox, "features/1", Some("2020-01-01T00:00:00Z"));
+        put(&outbox, "features/2", Some("2020-01-01T00:00:00Z"));
+        let remote = Remote::new(&[("1", "2020-01-01T00:00:00Z"), ("2", "2020-01-01T00:00:00Z")]);
+        let summary = remote.replay(&outbox).unwrap();
+        assert_eq!(summary, Some("outbox: 2 sent".to_string()));
+        assert_eq!(
+            *remote.sent.borrow(),
+            vec![
+                format!("PUT {}/features/1", API),
+                format!("PUT {}/features/2", API)
+            ]
+        );
+        assert!(outbox.entries().unwrap().is_empty());
+        clean(&outbox);
+    }
+
+    #[test]
+    fn second_write_to_the_same_record_is_not_a_conflict() {
+        let outbox = temp_outbox("same");
+        put(&outbox, "features/1", Some("2020-01-01T00:00:00Z"));
+        put(&outbox, "features/1", Some("2020-01-01T00:00:00Z"));
+        let remote = Remote::new(&[("1", "2020-01-01T00:00:00Z")]);
+        remote.replay(&outbox).unwrap();
+        assert_eq!(remote.sent.borrow().len(), 2);
+        assert!(rejected(&outbox).is_empty());
+        clean(&outbox);
+    }
+
+    #[test]
+    fn record_changed_since_the_saved_copy_conflicts() {
+        let outbox = temp_outbox("changed");
+        put(&outbox, "features/1", Some("2020-01-01T00:00:00Z"));
+        put(&outbox, "features/2", Some("2020-01-01T00:00:00Z"));
+        let remote = Remote::new(&[("1", "2020-02-01T00:00:00Z"), ("2", "2020-01-01T00:00:00Z")]);
+        let summary = remote.replay(&outbox).unwrap().unwrap();
+        assert!(summary.starts_with("outbox: 1 sent, 1 not sent"));
+        assert_eq!(
+            *remote.sent.borrow(),
+            vec![format!("PUT {}/features/2", API)]
+        );
+        let rejected = rejected(&outbox);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].uri, format!("{}/features/1", API));
+        clean(&outbox);
+    }
+
+    #[test]
+    fn missing_record_conflicts() {
+        let outbox = temp_outbox("missing");
+        put(&outbox, "features/9", None);
+        let remote = Remote::new(&[]);
+        remote.replay(&outbox).unwrap();
+        assert!(remote.sent.borrow().is_empty());
+        assert_eq!(rejected(&outbox).len(), 1);
+        clean(&outbox);
+    }
+
+    #[test]
+    fn writes_to_an_offline_create_go_to_its_real_id() {
+        let outbox = temp_outbox("created");
+        let created = post(&outbox, "releases/5/features");
+        put(&outbox, &format!("features/{}", created.id), None);
+        post(
+            &outbox,
+            &format!("features/{}/requirements", created.reference_num),
+        );
+        let remote = Remote::new(&[]);
+        remote.replay(&outbox).unwrap();
+        assert_eq!(
+            *remote.sent.borrow(),
+            vec![
+                format!("POST {}/releases/5/features", API),
+                format!("PUT {}/features/100", API),
+                format!("POST {}/features/APP-7/requirements", API),
+            ]
+        );
+        assert!(rejected(&outbox).is_empty());
+        clean(&outbox);
+    }
+
+    #[test]
+    fn writes_to_a_create_that_failed_are_not_sent() {
+        let outbox = temp_outbox("lost");
+        let created = post(&outbox, "releases/5/features");
+        put(&outbox, &format!("features/{}", created.id), None);
+        let sent = RefCell::new(0);
+        replay_with(
+            &outbox,
+            |_, _, _, _| {
+                *sent.borrow_mut() += 1;
+                Err(AhaError::Status {
+                    status: 422,
+                    body: "bad release".to_string(),
+                })
+            },
+            |_, _| Ok(json!({})),
+        )
+        .unwrap();
+        assert_eq!(*sent.borrow(), 1);
+        assert_eq!(rejected(&outbox).len(), 2);
+        clean(&outbox);
+    }
+
+    #[test]
+    fn unreachable_aha_keeps_the_rest_queued() {
+        let outbox = temp_outbox("queued");
+        let created = post(&outbox, "releases/5/features");
+        put(&outbox, &format!("features/{}", created.id), None);
+        let sent = RefCell::new(0);
+        let result = replay_with(
+            &outbox,
+            |_, _, _, _| {
+                *sent.borrow_mut() += 1;
+                if *sent.borrow() == 1 {
+                    Ok(json!({ "id": "100", "reference_num": "APP-7" }))
+                } else {
+                    Err(AhaError::RateLimited(30))
+                }
+            },
+            |_, _| Ok(json!({})),
+        );
+        assert!(result.is_err());
+        let entries = outbox.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        // saved with the real id for the next run
+        assert_eq!(entries[0].uri, format!("{}/features/100", API));
+        clean(&outbox);
+    }
+}
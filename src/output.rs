@@ -0,0 +1,287 @@
+use super::aha::{Feature, Product, Release, Requirement};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Jsonl,
+    Csv,
+    Tsv,
+    Table,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "jsonl" => Ok(Format::Jsonl),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "table" => Ok(Format::Table),
+            _ => Err(format!(
+                "unknown format {}, expected json, jsonl, csv, tsv or table",
+                format
+            )),
+        }
+    }
+}
+
+const COLUMNS: [&str; 5] = ["reference_num", "name", "status", "assignee", "url"];
+
+// the same columns for every kind of record so scripts can rely on them
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Row {
+    pub reference_num: String,
+    pub name: String,
+    pub status: String,
+    pub assignee: String,
+    pub url: String,
+}
+
+impl Row {
+    fn values(&self) -> [&str; 5] {
+        [
+            &self.reference_num,
+            &self.name,
+            &self.status,
+            &self.assignee,
+            &self.url,
+        ]
+    }
+}
+
+impl From<&Product> for Row {
+    fn from(product: &Product) -> Self {
+        Row {
+            reference_num: product.reference_prefix.clone().unwrap_or_default(),
+            name: product.name.clone(),
+            url: product.url.clone().unwrap_or_default(),
+            ..Row::default()
+        }
+    }
+}
+
+impl From<&Release> for Row {
+    fn from(release: &Release) -> Self {
+        // releases have no workflow, report the schedule instead
+        let status = if release.parking_lot.unwrap_or(false) {
+            "Parking lot".to_string()
+        } else {
+            release.release_date.clone().unwrap_or_default()
+        };
+        Row {
+            reference_num: release.reference_num.clone().unwrap_or_default(),
+            name: release.name.clone(),
+            status,
+            url: release.url.clone().unwrap_or_default(),
+            ..Row::default()
+        }
+    }
+}
+
+impl From<&Feature> for Row {
+    fn from(feature: &Feature) -> Self {
+        Row {
+            reference_num: feature.reference_num.clone(),
+            name: feature.name.clone(),
+            status: feature.status_name().to_string(),
+            assignee: feature
+                .assigned_to_user
+                .as_ref()
+                .map(|user| user.name.clone())
+                .unwrap_or_default(),
+            url: feature.url.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&Requirement> for Row {
+    fn from(requirement: &Requirement) -> Self {
+        Row {
+            reference_num: requirement.reference_num.clone(),
+            name: requirement.name.clone(),
+            status: requirement.status_name().to_string(),
+            assignee: requirement
+                .assigned_to_user
+                .as_ref()
+                .map(|user| user.name.clone())
+                .unwrap_or_default(),
+            url: requirement.url.clone().unwrap_or_default(),
+        }
+    }
+}
+
+// a feature followed by its requirements
+pub fn feature_rows(features: &[Feature]) -> Vec<Row> {
+    let mut rows = vec![];
+    for feature in features.iter() {
+        rows.push(Row::from(feature));
+        for requirement in feature.requirements.iter() {
+            rows.push(Row::from(requirement));
+        }
+    }
+    rows
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// tabs and newlines would break the columns so they become spaces
+fn tsv_field(value: &str) -> String {
+    value.replace(|c| c == '\t' || c == '\n' || c == '\r', " ")
+}
+
+// one tab separated line per row, what the list commands print without --format
+pub fn tsv_lines(rows: &[Row]) -> Vec<String> {
+    rows.iter()
+        .map(|row| {
+            row.values()
+                .iter()
+                .map(|value| tsv_field(value))
+                .collect::<Vec<String>>()
+                .join("\t")
+        })
+        .collect()
+}
+
+fn table(rows: &[Row]) -> String {
+    let mut widths: Vec<usize> = COLUMNS.iter().map(|column| column.len()).collect();
+    for row in rows.iter() {
+        for (i, value) in row.values().iter().enumerate() {
+            widths[i] = widths[i].max(tsv_field(value).chars().count());
+        }
+    }
+    let line = |values: Vec<String>| -> String {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("{:width$}", value, width = widths[i]))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = vec![line(COLUMNS.iter().map(|c| c.to_string()).collect())];
+    lines.push(line(
+        widths.iter().map(|width| "-".repeat(*width)).collect(),
+    ));
+    for row in rows.iter() {
+        lines.push(line(row.values().iter().map(|v| tsv_field(v)).collect()));
+    }
+    lines.join("\n")
+}
+
+pub fn render(format: Format, rows: &[Row]) -> String {
+    match format {
+        Format::Json => serde_json::to_string_pretty(rows).unwrap_or_default(),
+        Format::Jsonl => rows
+            .iter()
+            .map(|row| serde_json::to_string(row).unwrap_or_default())
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Format::Csv => std::iter::once(COLUMNS.join(","))
+            .chain(rows.iter().map(|row| {
+                row.values()
+                    .iter()
+                    .map(|value| csv_field(value))
+                    .collect::<Vec<String>>()
+                    .join(",")
+            }))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Format::Tsv => std::iter::once(COLUMNS.join("\t"))
+            .chain(tsv_lines(rows))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Format::Table => table(rows),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row {
+                reference_num: "APP-1".to_string(),
+                name: "Export, \"fast\"".to_string(),
+                status: "Ready".to_string(),
+                assignee: "Sam".to_string(),
+                url: "https://example.aha.io/features/APP-1".to_string(),
+            },
+            Row {
+                reference_num: "APP-1-1".to_string(),
+                name: "tabs\tand\nlines".to_string(),
+                ..Row::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn csv_field_quotes_only_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn csv_has_a_header() {
+        let csv = render(Format::Csv, &rows());
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "reference_num,name,status,assignee,url");
+        assert_eq!(
+            lines[1],
+            "APP-1,\"Export, \"\"fast\"\"\",Ready,Sam,https://example.aha.io/features/APP-1"
+        );
+    }
+
+    #[test]
+    fn tsv_has_a_header_and_flat_fields() {
+        let tsv = render(Format::Tsv, &rows());
+        let lines: Vec<&str> = tsv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "reference_num\tname\tstatus\tassignee\turl");
+        assert_eq!(lines[2], "APP-1-1\ttabs and lines\t\t\t");
+    }
+
+    #[test]
+    fn tsv_lines_have_no_header() {
+        let lines = tsv_lines(&rows());
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("APP-1\t"));
+    }
+
+    #[test]
+    fn jsonl_is_one_object_per_line() {
+        let jsonl = render(Format::Jsonl, &rows());
+        let lines: Vec<&str> = jsonl.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["reference_num"], "APP-1");
+    }
+
+    #[test]
+    fn table_pads_columns() {
+        let table = render(Format::Table, &rows());
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("reference_num  name"));
+        assert!(lines[1].starts_with("-------------  "));
+        assert_eq!(lines[2].find("Export"), lines[0].find("name"));
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!("TSV".parse::<Format>(), Ok(Format::Tsv));
+        assert!("xml".parse::<Format>().is_err());
+    }
+}
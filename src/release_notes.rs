@@ -0,0 +1,203 @@
+use super::aha::{Feature, Release};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    Status,
+    Tag,
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(group_by: &str) -> Result<Self, Self::Err> {
+        match group_by.to_lowercase().as_str() {
+            "status" => Ok(GroupBy::Status),
+            "tag" => Ok(GroupBy::Tag),
+            _ => Err(format!(
+                "unknown grouping {}, expected status or tag",
+                group_by
+            )),
+        }
+    }
+}
+
+// sections in the order the workflow lists them, features keep the release order
+fn group<'a>(features: &'a [Feature], group_by: GroupBy) -> Vec<(String, Vec<&'a Feature>)> {
+    let mut groups: Vec<(i64, String, Vec<&Feature>)> = vec![];
+    for feature in features.iter().filter(|feature| feature.needs_notes()) {
+        let keys: Vec<(i64, String)> = match group_by {
+            GroupBy::Status => {
+                let position = feature
+                    .workflow_status
+                    .as_ref()
+                    .and_then(|status| status.position)
+                    .unwrap_or(0);
+                vec![(position, feature.status_name().to_string())]
+            }
+            // a feature with several tags shows up under each of them
+            GroupBy::Tag if feature.tags.is_empty() => vec![(1, "Other".to_string())],
+            GroupBy::Tag => feature.tags.iter().map(|tag| (0, tag.clone())).collect(),
+        };
+        for (position, name) in keys {
+            match groups.iter_mut().find(|(_, group, _)| *group == name) {
+                Some((_, _, list)) => list.push(feature),
+                None => groups.push((position, name, vec![feature])),
+            }
+        }
+    }
+    groups.sort_by_key(|(position, _, _)| *position);
+    groups
+        .into_iter()
+        .map(|(_, name, list)| (name, list))
+        .collect()
+}
+
+fn title(release: &Release) -> String {
+    match release.release_date {
+        Some(ref date) => format!("{} ({})", release.name, date),
+        None => release.name.clone(),
+    }
+}
+
+pub fn markdown(release: &Release, features: &[Feature], group_by: GroupBy) -> String {
+    let mut notes = format!("# {}\n", title(release));
+    let groups = group(features, group_by);
+    if groups.is_empty() {
+        notes.push_str("\nNo features need release notes.\n");
+    }
+    for (name, list) in groups {
+        notes.push_str(&format!("\n## {}\n", name));
+        for feature in list {
+            notes.push_str(&format!(
+                "\n### {} {}\n\n",
+                feature.reference_num, feature.name
+            ));
+            let description = html2md::parse_html(&feature.description.body);
+            if !description.trim().is_empty() {
+                notes.push_str(description.trim());
+                notes.push('\n');
+            }
+        }
+    }
+    notes
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// aha already stores descriptions as html so they are used as is
+pub fn html(release: &Release, features: &[Feature], group_by: GroupBy) -> String {
+    let mut notes = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape(&release.name),
+        escape(&title(release))
+    );
+    let groups = group(features, group_by);
+    if groups.is_empty() {
+        notes.push_str("<p>No features need release notes.</p>\n");
+    }
+    for (name, list) in groups {
+        notes.push_str(&format!("<h2>{}</h2>\n", escape(&name)));
+        for feature in list {
+            notes.push_str(&format!(
+                "<h3>{} {}</h3>\n{}\n",
+                escape(&feature.reference_num),
+                escape(&feature.name),
+                feature.description.body
+            ));
+        }
+    }
+    notes.push_str("</body>\n</html>\n");
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feature(reference: &str, status: (&str, i64), tags: &[&str], notes: bool) -> Feature {
+        serde_json::from_value(json!({
+            "id": reference,
+            "reference_num": reference,
+            "name": format!("{} <name>", reference),
+            "description": { "body": "<p>details</p>" },
+            "workflow_status": { "name": status.0, "position": status.1 },
+            "tags": tags,
+            "custom_fields": [
+                { "key": "release_notes1", "value": if notes { "Required" } else { "" } }
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn names(groups: &[(String, Vec<&Feature>)]) -> Vec<(String, Vec<String>)> {
+        groups
+            .iter()
+            .map(|(name, list)| {
+                let references = list.iter().map(|f| f.reference_num.clone()).collect();
+                (name.clone(), references)
+            })
+            .collect()
+    }
+
+    fn features() -> Vec<Feature> {
+        vec![
+            feature("APP-1", ("Shipped", 3), &["api", "ui"], true),
+            feature("APP-2", ("In review", 2), &[], true),
+            feature("APP-3", ("Shipped", 3), &["ui"], true),
+            feature("APP-4", ("In review", 2), &["api"], false),
+        ]
+    }
+
+    #[test]
+    fn group_by_status_follows_the_workflow_order() {
+        let features = features();
+        assert_eq!(
+            names(&group(&features, GroupBy::Status)),
+            vec![
+                ("In review".to_string(), vec!["APP-2".to_string()]),
+                (
+                    "Shipped".to_string(),
+                    vec!["APP-1".to_string(), "APP-3".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn group_by_tag_repeats_features_and_puts_untagged_last() {
+        let features = features();
+        assert_eq!(
+            names(&group(&features, GroupBy::Tag)),
+            vec![
+                ("api".to_string(), vec!["APP-1".to_string()]),
+                (
+                    "ui".to_string(),
+                    vec!["APP-1".to_string(), "APP-3".to_string()]
+                ),
+                ("Other".to_string(), vec!["APP-2".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn html_escapes_names_but_keeps_descriptions() {
+        let release: Release =
+            serde_json::from_value(json!({ "name": "R&D", "release_date": "2020-05-01" })).unwrap();
+        let notes = html(&release, &features()[..1], GroupBy::Status);
+        assert!(notes.contains("<h1>R&amp;D (2020-05-01)</h1>"));
+        assert!(notes.contains("<h3>APP-1 APP-1 &lt;name&gt;</h3>\n<p>details</p>"));
+    }
+
+    #[test]
+    fn nothing_to_write() {
+        let release: Release = serde_json::from_value(json!({ "name": "R1" })).unwrap();
+        let notes = html(&release, &features()[3..], GroupBy::Status);
+        assert!(notes.contains("No features need release notes."));
+    }
+}
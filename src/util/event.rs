@@ -1,5 +1,5 @@
 //https://raw.githubusercontent.com/fdehau/tui-rs/master/examples/util/event.rs
-use std::io;
+use std::io::{self, Read};
 use std::sync::mpsc;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -8,33 +8,46 @@ use std::sync::{
 use std::thread;
 use std::time::Duration;
 
-use termion::event::Key;
-use termion::input::TermRead;
+use termion::event::{self as term_event, Key};
+
+use crate::worker::Reply;
+
+// terminals wrap pasted text in these once bracketed paste is turned on
+pub const ENABLE_BRACKETED_PASTE: &str = "\x1b[?2004h";
+pub const DISABLE_BRACKETED_PASTE: &str = "\x1b[?2004l";
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+const POLL_TIMEOUT: Duration = Duration::from_millis(50);
+// as big as the stdin buffer so reads skip it and nothing waits where poll can not see it
+const READ_SIZE: usize = 8 * 1024;
 
 pub enum Event<I> {
     Input(I),
+    Paste(String),
     Tick,
+    // a finished background request
+    Api(Reply),
 }
 
 /// A small event handler that wrap termion input and tick events. Each event
 /// type is handled in its own thread and returned to a common `Receiver`
 pub struct Events {
     rx: mpsc::Receiver<Event<Key>>,
+    tx: mpsc::Sender<Event<Key>>,
     input_handle: thread::JoinHandle<()>,
-    ignore_exit_key: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    idle: Arc<AtomicBool>,
     tick_handle: thread::JoinHandle<()>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Config {
-    pub exit_key: Key,
     pub tick_rate: Duration,
 }
 
 impl Default for Config {
     fn default() -> Config {
         Config {
-            exit_key: Key::Char('q'),
             tick_rate: Duration::from_millis(250),
         }
     }
@@ -47,29 +60,47 @@ impl Events {
 
     pub fn with_config(config: Config) -> Events {
         let (tx, rx) = mpsc::channel();
-        let ignore_exit_key = Arc::new(AtomicBool::new(false));
+        let paused = Arc::new(AtomicBool::new(false));
+        let idle = Arc::new(AtomicBool::new(false));
         let input_handle = {
             let tx = tx.clone();
-            let ignore_exit_key = ignore_exit_key.clone();
+            let paused = paused.clone();
+            let idle = idle.clone();
             thread::spawn(move || {
+                // stdin is read only after poll says it is ready so the thread can step
+                // aside while another program owns the terminal
                 let stdin = io::stdin();
-                for evt in stdin.keys() {
-                    match evt {
-                        Ok(key) => {
-                            if tx.send(Event::Input(key)).is_err() {
-                                return;
-                            }
-                            if !ignore_exit_key.load(Ordering::Relaxed) && key == config.exit_key {
-                                return;
-                            }
+                let mut buf = [0u8; READ_SIZE];
+                let mut pending: Vec<u8> = vec![];
+                let mut paste: Option<String> = None;
+                loop {
+                    if paused.load(Ordering::SeqCst) {
+                        idle.store(true, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(10));
+                        continue;
+                    }
+                    idle.store(false, Ordering::SeqCst);
+                    if !stdin_ready(POLL_TIMEOUT) || paused.load(Ordering::SeqCst) {
+                        continue;
+                    }
+                    let read = match stdin.lock().read(&mut buf) {
+                        Ok(0) => return,
+                        Ok(read) => read,
+                        Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(_) => return,
+                    };
+                    pending.extend_from_slice(&buf[..read]);
+                    // quitting is up to the main loop, it knows when a popup takes the key
+                    for event in parse_input(&mut pending, &mut paste) {
+                        if tx.send(event).is_err() {
+                            return;
                         }
-                        Err(_) => {}
                     }
                 }
             })
         };
         let tick_handle = {
-            let tx = tx;
+            let tx = tx.clone();
             thread::spawn(move || {
                 let tx = tx.clone();
                 loop {
@@ -80,7 +111,9 @@ impl Events {
         };
         Events {
             rx,
-            ignore_exit_key,
+            tx,
+            paused,
+            idle,
             input_handle,
             tick_handle,
         }
@@ -90,11 +123,177 @@ impl Events {
         self.rx.recv()
     }
 
-    pub fn disable_exit_key(&mut self) {
-        self.ignore_exit_key.store(true, Ordering::Relaxed);
+    // lets the worker threads report into the same loop as input and ticks
+    pub fn sender(&self) -> mpsc::Sender<Event<Key>> {
+        self.tx.clone()
+    }
+
+    // stops reading stdin so another program can have the terminal
+    pub fn pause(&self) {
+        self.idle.store(false, Ordering::SeqCst);
+        self.paused.store(true, Ordering::SeqCst);
+        // the thread may already be gone when stdin closed
+        for _ in 0..100 {
+            if self.idle.load(Ordering::SeqCst) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+}
+
+// the number of bytes in the first key or escape sequence, none when the read cut it off
+// and the rest is still to come. an escape that ends a read is the esc key itself
+fn sequence_len(bytes: &[u8]) -> Option<usize> {
+    let utf8_len = |byte: u8| match byte {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 1,
+    };
+    let len = match bytes {
+        [] => return None,
+        [0x1B] => 1,
+        [0x1B, b'[', b'[', ..] => 4,
+        [0x1B, b'[', b'M', ..] => 6,
+        [0x1B, b'[', rest @ ..] => {
+            // parameters run until a final byte between @ and ~
+            let end = rest.iter().position(|byte| (0x40..=0x7E).contains(byte))?;
+            end + 3
+        }
+        [0x1B, b'O', ..] => 3,
+        [0x1B, byte, ..] => 1 + utf8_len(*byte),
+        [byte, ..] => utf8_len(*byte),
+    };
+    if bytes.len() < len {
+        None
+    } else {
+        Some(len)
+    }
+}
+
+// the events in the bytes read so far. a sequence cut off at the end stays in pending
+// and is finished by the next read
+fn parse_input(pending: &mut Vec<u8>, paste: &mut Option<String>) -> Vec<Event<Key>> {
+    let mut events = vec![];
+    let mut start = 0;
+    while let Some(len) = sequence_len(&pending[start..]) {
+        let sequence = &pending[start..start + len];
+        start += len;
+        if sequence == PASTE_START {
+            *paste = Some(String::new());
+            continue;
+        }
+        if sequence == PASTE_END {
+            if let Some(text) = paste.take() {
+                events.push(Event::Paste(text));
+            }
+            continue;
+        }
+        let mut rest = sequence[1..].iter().map(|byte| Ok(*byte));
+        let key = match term_event::parse_event(sequence[0], &mut rest) {
+            Ok(term_event::Event::Key(key)) => key,
+            // termion has no key for a lone escape
+            Err(_) if sequence == [0x1B] => Key::Esc,
+            // mouse reports, focus reports and sequences termion can not read are not keys
+            Ok(_) | Err(_) => continue,
+        };
+        // pasted keys are text, not commands
+        match paste.as_mut() {
+            Some(text) => {
+                if let Key::Char(c) = key {
+                    text.push(c);
+                }
+            }
+            None => events.push(Event::Input(key)),
+        }
+    }
+    pending.drain(..start);
+    events
+}
+
+fn stdin_ready(timeout: Duration) -> bool {
+    let mut fds = libc::pollfd {
+        fd: libc::STDIN_FILENO,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut fds, 1, timeout.as_millis() as libc::c_int) > 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(events: Vec<Event<Key>>) -> Vec<Key> {
+        events
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Input(key) => Some(key),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn read(pending: &mut Vec<u8>, paste: &mut Option<String>, bytes: &[u8]) -> Vec<Key> {
+        pending.extend_from_slice(bytes);
+        keys(parse_input(pending, paste))
+    }
+
+    #[test]
+    fn esc_at_the_end_of_a_read_is_a_key() {
+        let (mut pending, mut paste) = (vec![], None);
+        assert_eq!(read(&mut pending, &mut paste, b"\x1b"), vec![Key::Esc]);
+        assert_eq!(
+            read(&mut pending, &mut paste, b"j\x1b"),
+            vec![Key::Char('j'), Key::Esc]
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn escape_sequence_split_across_reads() {
+        let (mut pending, mut paste) = (vec![], None);
+        assert_eq!(
+            read(&mut pending, &mut paste, b"a\x1b[20"),
+            vec![Key::Char('a')]
+        );
+        assert_eq!(pending, b"\x1b[20".to_vec());
+        assert_eq!(
+            read(&mut pending, &mut paste, b"~\x1b[A"),
+            vec![Key::F(9), Key::Up]
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn utf8_char_split_across_reads() {
+        let (mut pending, mut paste) = (vec![], None);
+        let bytes = "é".as_bytes();
+        assert_eq!(read(&mut pending, &mut paste, &bytes[..1]), vec![]);
+        assert_eq!(
+            read(&mut pending, &mut paste, &bytes[1..]),
+            vec![Key::Char('é')]
+        );
     }
 
-    pub fn enable_exit_key(&mut self) {
-        self.ignore_exit_key.store(false, Ordering::Relaxed);
+    #[test]
+    fn paste_is_text_not_keys() {
+        let (mut pending, mut paste) = (vec![], None);
+        pending.extend_from_slice(b"\x1b[200~q\nx\x1b[201~j");
+        let events = parse_input(&mut pending, &mut paste);
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            Event::Paste(text) => assert_eq!(text, "q\nx"),
+            _ => panic!("expected a paste"),
+        }
+        match events[1] {
+            Event::Input(key) => assert_eq!(key, Key::Char('j')),
+            _ => panic!("expected a key"),
+        }
     }
 }
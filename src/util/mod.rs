@@ -169,7 +169,75 @@ impl<T> StatefulList<T> {
         self.state.select(Some(i));
     }
 
+    // a counted move stops at either end, a single step wraps like next and previous
+    pub fn step(&mut self, count: usize, down: bool) {
+        if count <= 1 {
+            if down {
+                self.next();
+            } else {
+                self.previous();
+            }
+            return;
+        }
+        let index = match self.state.selected() {
+            Some(i) if down => i.saturating_add(count),
+            Some(i) => i.saturating_sub(count),
+            None => 0,
+        };
+        self.select(index);
+    }
+
     pub fn unselect(&mut self) {
         self.state.select(None);
     }
+
+    // clamps to the last item
+    pub fn select(&mut self, index: usize) {
+        if self.items.is_empty() {
+            self.state.select(None);
+        } else {
+            self.state.select(Some(index.min(self.items.len() - 1)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(selected: usize) -> StatefulList<usize> {
+        let mut list = StatefulList::with_items((0..5).collect());
+        list.select(selected);
+        list
+    }
+
+    #[test]
+    fn single_step_wraps() {
+        let mut last = list(4);
+        last.step(1, true);
+        assert_eq!(last.state.selected(), Some(0));
+        let mut first = list(0);
+        first.step(1, false);
+        assert_eq!(first.state.selected(), Some(4));
+    }
+
+    #[test]
+    fn counted_step_stops_at_the_ends() {
+        let mut down = list(1);
+        down.step(10, true);
+        assert_eq!(down.state.selected(), Some(4));
+        let mut up = list(3);
+        up.step(10, false);
+        assert_eq!(up.state.selected(), Some(0));
+        let mut middle = list(1);
+        middle.step(2, true);
+        assert_eq!(middle.state.selected(), Some(3));
+    }
+
+    #[test]
+    fn step_in_an_empty_list_selects_nothing() {
+        let mut empty: StatefulList<usize> = StatefulList::new();
+        empty.step(3, true);
+        assert_eq!(empty.state.selected(), None);
+    }
 }
@@ -0,0 +1,203 @@
+use super::aha::{
+    AhaError, Comment, Description, Feature, Product, Release, Requirement, Todo, User, Workflow,
+    WorkflowStatus,
+};
+use super::util::event::Event;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use termion::event::Key;
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pane {
+    Products,
+    Releases,
+    Features,
+    Search,
+    Comments,
+    Todos,
+    // workflows and users for the pickers, the pull request lookup
+    Picker,
+    // writes, they all report back and esc does not drop them
+    Save,
+}
+
+pub enum Response {
+    Products(Result<Vec<Product>, AhaError>),
+    Releases(Result<Vec<Release>, AhaError>),
+    // features arrive a page at a time, the first one replaces the list
+    FirstFeatures(Result<Vec<Feature>, AhaError>),
+    Features(Result<Vec<Feature>, AhaError>),
+    Search(Result<Vec<Feature>, AhaError>),
+    SearchResult(Result<Feature, AhaError>),
+    // comments of the feature or requirement with this id
+    Comments(String, Result<Vec<Comment>, AhaError>),
+    Todos(String, Result<Vec<Todo>, AhaError>),
+    // the current status id picks the workflow to list
+    Workflows(Option<String>, Result<Vec<Workflow>, AhaError>),
+    Users(Result<Vec<User>, AhaError>),
+    ReleaseNotes(Release, Result<Vec<Feature>, AhaError>),
+    // writes carry the id of the feature or requirement they changed
+    FeatureCreated(String, Result<Feature, AhaError>),
+    // the release id and the reference_num of the feature it was added to
+    RequirementCreated(String, String, Result<Requirement, AhaError>),
+    Status(String, String, Result<Option<WorkflowStatus>, AhaError>),
+    Assigned(String, String, Result<Option<User>, AhaError>),
+    PullRequest(String, String, String, Result<(), failure::Error>),
+    // the pull request of the current git branch
+    PullRequestFound(Result<String, failure::Error>),
+    Description(String, Result<Description, AhaError>),
+    Edited(String, String, Result<Feature, AhaError>),
+    CommentPosted(String, Result<Comment, AhaError>),
+    // the to-do id and its new status
+    TodoUpdated(String, String, Result<(), AhaError>),
+    TodoAdded(String, Result<Todo, AhaError>),
+    // the pane is showing a cached copy past its ttl while a fresh one loads
+    Stale(bool),
+    Done,
+}
+
+pub struct Reply {
+    pub id: u64,
+    pub response: Response,
+}
+
+// handed to the job so it can answer and notice when it is no longer wanted
+pub struct Reporter {
+    id: u64,
+    tx: Sender<Event<Key>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Reporter {
+    // false once the job was cancelled or the ui is gone, the job should stop
+    pub fn send(&self, response: Response) -> bool {
+        !self.cancelled.load(Ordering::SeqCst)
+            && self
+                .tx
+                .send(Event::Api(Reply {
+                    id: self.id,
+                    response,
+                }))
+                .is_ok()
+    }
+}
+
+struct Job {
+    id: u64,
+    pane: Pane,
+    cancelled: Arc<AtomicBool>,
+}
+
+// runs requests on their own threads and reports back through the events channel.
+// replies carry the job id so anything cancelled or replaced is dropped
+pub struct Worker {
+    tx: Sender<Event<Key>>,
+    next_id: u64,
+    jobs: Vec<Job>,
+    frame: usize,
+}
+
+impl Worker {
+    pub fn new(tx: Sender<Event<Key>>) -> Worker {
+        Worker {
+            tx,
+            next_id: 0,
+            jobs: vec![],
+            frame: 0,
+        }
+    }
+
+    // a new load for a pane replaces the one still running there
+    pub fn run<F>(&mut self, pane: Pane, work: F)
+    where
+        F: FnOnce(&Reporter) + Send + 'static,
+    {
+        self.cancel(pane);
+        self.start(pane, work);
+    }
+
+    // writes run next to each other, a second one does not cancel the first
+    pub fn save<F>(&mut self, work: F)
+    where
+        F: FnOnce(&Reporter) + Send + 'static,
+    {
+        self.start(Pane::Save, work);
+    }
+
+    fn start<F>(&mut self, pane: Pane, work: F)
+    where
+        F: FnOnce(&Reporter) + Send + 'static,
+    {
+        self.next_id += 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let reporter = Reporter {
+            id: self.next_id,
+            tx: self.tx.clone(),
+            cancelled: cancelled.clone(),
+        };
+        self.jobs.push(Job {
+            id: self.next_id,
+            pane,
+            cancelled,
+        });
+        thread::spawn(move || {
+            work(&reporter);
+            reporter.send(Response::Done);
+        });
+    }
+
+    // the pane a reply belongs to, none when it is stale
+    pub fn pane(&self, id: u64) -> Option<Pane> {
+        self.jobs
+            .iter()
+            .find(|job| job.id == id)
+            .map(|job| job.pane)
+    }
+
+    pub fn finish(&mut self, id: u64) {
+        self.jobs.retain(|job| job.id != id);
+    }
+
+    pub fn cancel(&mut self, pane: Pane) {
+        for job in self.jobs.iter().filter(|job| job.pane == pane) {
+            job.cancelled.store(true, Ordering::SeqCst);
+        }
+        self.jobs.retain(|job| job.pane != pane);
+    }
+
+    // true when a load was running, writes are left to finish
+    pub fn cancel_all(&mut self) -> bool {
+        let mut cancelled = false;
+        for job in self.jobs.iter().filter(|job| job.pane != Pane::Save) {
+            job.cancelled.store(true, Ordering::SeqCst);
+            cancelled = true;
+        }
+        self.jobs.retain(|job| job.pane == Pane::Save);
+        cancelled
+    }
+
+    pub fn is_loading(&self, pane: Pane) -> bool {
+        self.jobs.iter().any(|job| job.pane == pane)
+    }
+
+    pub fn tick(&mut self) {
+        self.frame = (self.frame + 1) % SPINNER.len();
+    }
+
+    // the pane title with a spinner while its load is running
+    pub fn title(&self, title: &str, pane: Pane) -> String {
+        self.busy_title(title, &[pane])
+    }
+
+    pub fn busy_title(&self, title: &str, panes: &[Pane]) -> String {
+        if panes.iter().any(|pane| self.is_loading(*pane)) {
+            format!("{} {}", title, SPINNER[self.frame])
+        } else {
+            title.to_string()
+        }
+    }
+}